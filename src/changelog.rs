@@ -3,11 +3,17 @@
 
 use chrono::prelude::*;
 use chrono::MIN_DATE;
-use commit::{Commit, CommitList, Line};
-use git;
-use input::{Configuration, Conventions};
-use std::collections::HashMap;
+use commit::{
+    parse_breaking_footer, parse_conventional_subject, trailing_trailers, Commit, CommitList, Line,
+    Reference,
+};
+use error::Error;
+use forge::Forge;
+use git::{GitBackend, GitCommand};
+use input::{Configuration, Conventions, Parsing};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::str;
+use version::{bump_for, next_version, ReleaseTags};
 
 /// A categorized changelog
 #[derive(Debug, Default, Serialize, Eq, PartialEq)]
@@ -18,14 +24,43 @@ pub struct ChangeLog {
     /// A list of "interesting" commits in the range.
     pub commits: Vec<Commit>,
 
+    /// A list of everyone who authored or co-authored commits in the range, most active first.
+    pub contributors: Vec<Contributor>,
+
     /// The fetch url of the remote (useful for change number links)
     pub remote_url: Option<String>,
 
+    /// The url prefix that links to a commit when followed by its SHA
+    pub commit_url: Option<String>,
+
+    /// The url prefix that links to a pull (or merge) request when followed by its number
+    pub pr_url: Option<String>,
+
+    /// The url prefix that links to an issue when followed by its number
+    pub issue_url: Option<String>,
+
+    /// The url that compares the two ends of the revision range (when the range has two ends)
+    pub compare_url: Option<String>,
+
     /// The revision range for commits in this changelog
     pub range: String,
 
+    /// The package the changes are for (when the configuration picks one)
+    pub package: Option<String>,
+
     /// The time range for the commits in this changelog
     pub date: String,
+
+    /// The time the release was tagged (RFC 2822), for the tagged releases of a history
+    pub tag_time: Option<String>,
+
+    /// The version the release tag names (as the tag pattern reads it), for the tagged releases of a
+    /// history
+    pub version: Option<String>,
+
+    /// The semantic version the changes call for, after the version the range starts at (if any change
+    /// has a keyword with a `bump`)
+    pub next_version: Option<String>,
 }
 
 /// Changes grouped by scope (e.g. "API", "Documentation", etc.).
@@ -45,7 +80,52 @@ pub struct Category {
     pub title: String,
 
     /// A list of changes in this category groups across all commits in range.
-    pub changes: Vec<String>,
+    pub changes: Vec<Change>,
+}
+
+/// A person who contributed to the commits in a changelog.
+#[derive(Clone, Debug, Default, Serialize, Eq, PartialEq)]
+pub struct Contributor {
+    /// The name (as mapped by `.mailmap`)
+    pub name: String,
+
+    /// The email (as mapped by `.mailmap`)
+    pub email: String,
+
+    /// The number of commits they authored or co-authored
+    pub commits: usize,
+
+    /// They had no commits in the repository before the range (only known for changelogs created from
+    /// a `git log`)
+    pub first_time: bool,
+}
+
+/// A single change, along with the commit it came from.
+#[derive(Clone, Debug, Default, Serialize, Eq, PartialEq)]
+pub struct Change {
+    /// The change text
+    pub text: String,
+
+    /// The SHA of the commit
+    pub sha: String,
+
+    /// The author of the commit
+    pub author: String,
+
+    /// The timestamp of the commit
+    pub time: String,
+
+    /// The change number of the commit
+    pub number: Option<u64>,
+
+    /// The references on the commit subject
+    pub references: Vec<Reference>,
+
+    /// The scope tag the change was recorded with
+    pub scope_tag: String,
+
+    /// The category tag the change was recorded with
+    pub category_tag: String,
 }
 
 impl ChangeLog {
@@ -60,42 +140,124 @@ impl ChangeLog {
     }
 
     /// Create a changelog from the given `git log` arguments
-    pub fn from_log(mut args: Vec<String>, config: &Configuration) -> Self {
-        // The default `git log` behavior is to list _all_ commits
+    pub fn from_log(args: Vec<String>, config: &Configuration) -> Self {
+        Self::from_log_with(args, config, &GitCommand::default())
+    }
+
+    /// Create a changelog from the given `git log` arguments, reading the log with the given backend
+    pub fn from_log_with(
+        mut args: Vec<String>,
+        config: &Configuration,
+        backend: &dyn GitBackend,
+    ) -> Self {
+        // The default `git log` behavior is to list _all_ commits (a bad configuration fails below)
         if args.is_empty() {
-            // The default `git changelog` behavior is to list _all_ commits since last tag.
-            if let Ok(Some(tag)) = git::last_tag() {
-                args.push(format!("{}..HEAD", tag))
-            } else {
-                // If there are no tags, default to the last commit
-                args.push(String::from("HEAD^..HEAD"))
-            }
+            args.extend(default_range(config, backend).ok());
         }
 
         let header = args.join(" ");
-        let range = CommitList::from(args);
-        info!("Using revision range '{}'", range);
+        Self::try_from_log_with(args, config, backend).unwrap_or_else(|why| {
+            error!("Invalid log input {} (Reason: {})", header, why);
+            let mut log = Self::from_with(Vec::new().into_iter(), config, backend);
+            log.range = header;
+            log
+        })
+    }
 
-        // Compute the change log
-        let mut log = Self::from(range, config);
+    /// Create a changelog from the given `git log` arguments, failing if the configuration is invalid or
+    /// git cannot read the log
+    pub fn try_from_log(args: Vec<String>, config: &Configuration) -> Result<Self, Error> {
+        Self::try_from_log_with(args, config, &GitCommand::default())
+    }
 
-        // Record the range we used (it is used by the template)
-        log.range = header;
+    /// Create a changelog from the given `git log` arguments with the given backend, failing if the
+    /// configuration is invalid or the backend cannot read the log
+    pub fn try_from_log_with(
+        mut args: Vec<String>,
+        config: &Configuration,
+        backend: &dyn GitBackend,
+    ) -> Result<Self, Error> {
+        config.conventions.validate()?;
+
+        // The default `git log` behavior is to list _all_ commits
+        if args.is_empty() {
+            args.push(default_range(config, backend)?);
+        }
 
         // Done.
+        let known = known_contributors(&args, backend);
+        try_from_log_known(args, &known, config, backend)
+    }
+
+    /// Create a changelog from the given commits, as picked by the given `git log` arguments (they
+    /// link the range). The contributors whose identities are not known are new to the range.
+    pub fn from_log_commits<T: Iterator<Item = Commit>>(
+        commits: T,
+        log_args: Vec<String>,
+        known: &HashSet<String>,
+        config: &Configuration,
+        backend: &dyn GitBackend,
+    ) -> Self {
+        // Compute the change log
+        let mut log = Self::from_with(commits, config, backend);
+
+        // Mark the contributors that had no commits before the range
+        for contributor in &mut log.contributors {
+            contributor.first_time = !known.contains(&contributor.identity());
+        }
+
+        // Link the comparison of the range ends, if there are two
+        if let (Some(url), [ref range]) = (log.remote_url.as_ref(), log_args.as_slice()) {
+            if let Some(index) = range.find("..").filter(|_| !range.contains("...")) {
+                let (from, to) = (&range[..index], &range[index + 2..]);
+                let from = if from.is_empty() { "HEAD" } else { from };
+                let to = if to.is_empty() { "HEAD" } else { to };
+                let forge = config.output.forge.unwrap_or_else(|| Forge::detect(url));
+                log.compare_url = Some(forge.compare_url(url, from, to));
+            }
+        }
+
+        // Work out the version the changes call for (ranges that start at no version follow the last tag)
+        let tags = ReleaseTags::new(&config.conventions).ok();
+        let current = match (range_start(&log_args), tags) {
+            (Some(start), Some(tags)) => tags
+                .version(&start)
+                .or_else(|| tags.last(backend).and_then(|t| tags.version(&t))),
+            _ => None,
+        };
+        log.next_version = bump_for(&log, &config.conventions)
+            .map(|bump| next_version(current.as_ref(), bump).to_string());
+
+        // Record the range we used (it is used by the template)
+        log.range = log_args.join(" ");
         log
     }
 
     /// Create a changelog from the given commits using the given conventions
     pub fn from<T: Iterator<Item = Commit>>(commits: T, config: &Configuration) -> Self {
+        Self::from_with(commits, config, &GitCommand::default())
+    }
+
+    /// Create a changelog from the given commits, looking up the remote with the given backend
+    pub fn from_with<T: Iterator<Item = Commit>>(
+        commits: T,
+        config: &Configuration,
+        backend: &dyn GitBackend,
+    ) -> Self {
         // Initialize a intermediate raw report
         let mut raw = RawReport::new();
 
         // Initialize the final change log
         let mut changelog = ChangeLog::default();
 
+        // The contacts of the people behind each commit
+        let mut contacts = Vec::new();
+
         // Walk through each commit in the range
         for commit in commits {
+            // Remember who contributed it
+            contacts.push(commit.contacts());
+
             // Offer it to the raw report
             if raw.add(&commit, &config.conventions) {
                 // Inform the user we're picking this one
@@ -125,23 +287,231 @@ impl ChangeLog {
             }
         }
 
+        // Count the commits of each contributor
+        changelog.contributors = contributors(contacts, backend);
+
         // Add the remote url, if we have one (it's used by links to commits and PRs)
         let remote = config.output.remote.as_deref().unwrap_or("origin");
-        changelog.remote_url = git::get_remote_url(&remote).unwrap_or(None);
+        changelog.remote_url = backend.get_remote_url(remote).unwrap_or(None);
+
+        // Add the links to the forge pages
+        if let Some(ref url) = changelog.remote_url {
+            let forge = config.output.forge.unwrap_or_else(|| Forge::detect(url));
+            changelog.commit_url = Some(forge.commit_url(url));
+            changelog.pr_url = Some(forge.pr_url(url));
+            changelog.issue_url = Some(forge.issue_url(url));
+        }
 
         // Add the last change date
         changelog.date = raw.date.format("%Y-%m-%d").to_string();
 
+        // Name the package, if there is one
+        changelog.package = config.package.as_ref().map(|p| p.name.clone());
+
         changelog
     }
 }
 
+impl Contributor {
+    /// Get the key that identifies the contributor (see `identity`)
+    pub fn identity(&self) -> String {
+        identity(&self.name, &self.email)
+    }
+}
+
+/// Create a changelog from the given `git log` arguments, taking the identities of the contributors
+/// before the range as given (instead of reading them from the log)
+pub fn try_from_log_known(
+    args: Vec<String>,
+    known: &HashSet<String>,
+    config: &Configuration,
+    backend: &dyn GitBackend,
+) -> Result<ChangeLog, Error> {
+    config.conventions.validate()?;
+    let patterns = config.conventions.reference_patterns();
+    let range = CommitList::try_from_backend(package_args(args.clone(), config)?, backend)?
+        .with_references(&patterns)?;
+    info!("Using revision range '{}'", range);
+    Ok(ChangeLog::from_log_commits(
+        range, args, known, config, backend,
+    ))
+}
+
+/// Get the identities of the people who contributed to the repository before the range the `git log`
+/// arguments pick
+fn known_contributors(args: &[String], backend: &dyn GitBackend) -> HashSet<String> {
+    let start = match range_start(args) {
+        Some(start) => start,
+        None => return HashSet::new(),
+    };
+    match backend.contacts_in_log(&[start]) {
+        Ok(contacts) => identities(contacts, backend),
+        Err(why) => {
+            warn!("Cannot find the earlier contributors (Reason: {})", why);
+            HashSet::new()
+        }
+    }
+}
+
+/// Get the default `git changelog` range, i.e. _all_ commits since the last release tag
+pub fn default_range(config: &Configuration, backend: &dyn GitBackend) -> Result<String, Error> {
+    Ok(
+        if let Some(tag) = ReleaseTags::new(&config.conventions)?.last(backend) {
+            format!("{}..HEAD", tag)
+        } else if config.package.is_some() {
+            // If a package has no tags, all its commits are new
+            String::from("HEAD")
+        } else {
+            // If there are no tags, default to the last commit
+            String::from("HEAD^..HEAD")
+        },
+    )
+}
+
+/// Limit the `git log` arguments to the files of the configured package (if any).
+///
+/// Git would match the files of either the package or the paths given after `--`, not both, so
+/// the two cannot be combined.
+pub fn package_args(mut args: Vec<String>, config: &Configuration) -> Result<Vec<String>, Error> {
+    let package = match config.package {
+        Some(ref package) => package,
+        None => return Ok(args),
+    };
+    let specs = package.pathspecs();
+    if specs.is_empty() {
+        return Ok(args);
+    }
+    match args.iter().position(|a| a == "--") {
+        Some(at) if at + 1 < args.len() => {
+            return Err(Error::InvalidConfig(format!(
+                "Paths ({}) cannot be combined with package '{}'",
+                args[at + 1..].join(" "),
+                package.name
+            )))
+        }
+        Some(_) => {}
+        None => args.push(String::from("--")),
+    }
+    args.extend(specs);
+    Ok(args)
+}
+
+/// Get the first excluded revision of the `git log` arguments (e.g. `v0.1.0` in `v0.1.0..HEAD`)
+fn range_start(args: &[String]) -> Option<String> {
+    for arg in args.iter().take_while(|a| *a != "--") {
+        if let Some(start) = arg.strip_prefix('^') {
+            return Some(start.to_owned());
+        }
+        if let Some(index) = arg.find("..") {
+            if !arg.contains("...") {
+                let start = &arg[..index];
+                return Some(if start.is_empty() { "HEAD" } else { start }.to_owned());
+            }
+        }
+    }
+    None
+}
+
+/// Split a `Name <email>` contact into its parts
+fn parse_contact(contact: &str) -> (String, String) {
+    match (contact.rfind('<'), contact.rfind('>')) {
+        (Some(open), Some(close)) if open < close => (
+            contact[..open].trim().to_owned(),
+            contact[open + 1..close].trim().to_owned(),
+        ),
+        _ => (contact.trim().to_owned(), String::new()),
+    }
+}
+
+/// The key that identifies a contributor (the email, or the name if there is no email)
+fn identity(name: &str, email: &str) -> String {
+    if email.is_empty() { name } else { email }.to_lowercase()
+}
+
+/// Map the contacts with the `.mailmap`, leaving them alone if that fails (or they have no email)
+fn mailmap(contacts: Vec<String>, backend: &dyn GitBackend) -> Vec<String> {
+    let has_email = |c: &String| !parse_contact(c).1.is_empty();
+    let mappable: Vec<String> = contacts.iter().filter(|c| has_email(c)).cloned().collect();
+    let mapped = match backend.check_mailmap(&mappable) {
+        Ok(mapped) if mapped.len() == mappable.len() => mapped,
+        Ok(_) => mappable,
+        Err(why) => {
+            warn!("Cannot apply .mailmap (Reason: {})", why);
+            mappable
+        }
+    };
+    let mut mapped = mapped.into_iter();
+    contacts
+        .into_iter()
+        .map(|c| {
+            if has_email(&c) {
+                mapped.next().unwrap_or(c)
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Get the identities of all the given contacts
+fn identities(contacts: Vec<String>, backend: &dyn GitBackend) -> HashSet<String> {
+    identity_map(contacts, backend).into_values().collect()
+}
+
+/// Map each of the given contacts to its identity (the `.mailmap` is checked once for all of them)
+pub fn identity_map(contacts: Vec<String>, backend: &dyn GitBackend) -> HashMap<String, String> {
+    let unique: BTreeSet<String> = contacts.into_iter().collect();
+    let unique: Vec<String> = unique.into_iter().collect();
+    let ids = mailmap(unique.clone(), backend).into_iter().map(|c| {
+        let (name, email) = parse_contact(&c);
+        identity(&name, &email)
+    });
+    unique.into_iter().zip(ids).collect()
+}
+
+/// Aggregate the contacts of each commit into a list of contributors
+fn contributors(contacts: Vec<Vec<String>>, backend: &dyn GitBackend) -> Vec<Contributor> {
+    // Map every distinct contact once
+    let unique: BTreeSet<&String> = contacts.iter().flat_map(|c| c.iter()).collect();
+    let unique: Vec<String> = unique.into_iter().cloned().collect();
+    let mapped: HashMap<&String, String> = unique
+        .iter()
+        .zip(mailmap(unique.clone(), backend))
+        .collect();
+
+    // Count each contributor once per commit
+    let mut found: Vec<Contributor> = Vec::new();
+    for commit in &contacts {
+        let mut seen = HashSet::new();
+        for contact in commit {
+            let (name, email) = parse_contact(&mapped[contact]);
+            let id = identity(&name, &email);
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            match found.iter_mut().find(|c| identity(&c.name, &c.email) == id) {
+                Some(known) => known.commits += 1,
+                None => found.push(Contributor {
+                    name,
+                    email,
+                    commits: 1,
+                    first_time: false,
+                }),
+            }
+        }
+    }
+
+    // Most active first
+    found.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+    found
+}
+
 /// Raw report
 struct RawReport<'a> {
     /// The date of the last change in the range
     date: Date<Utc>,
     /// Placeholder slots for aggregation
-    slots: HashMap<&'a str, HashMap<&'a str, Vec<String>>>,
+    slots: HashMap<&'a str, HashMap<&'a str, Vec<Change>>>,
 }
 
 impl<'a> RawReport<'a> {
@@ -158,20 +528,40 @@ impl<'a> RawReport<'a> {
         // Track if this commit brought anything interesting
         let mut interesting = false;
 
+        // With Conventional Commits, the subject can be tagged as well
+        let conventional = conventions.parsing == Parsing::Conventional;
+        if conventional {
+            if let Some(subject) = parse_conventional_subject(&commit.summary) {
+                interesting |= self.record(subject, commit, conventions);
+            }
+        }
+
         // The running current line
         let mut current = Line::default();
 
-        // Take each line
-        for line in commit {
+        // Take each line, up to the trailers left in the message
+        let lines = commit.message.trim_end().lines().count();
+        for line in commit
+            .into_iter()
+            .take(lines - trailing_trailers(&commit.message))
+        {
+            // With Conventional Commits, breaking change footers are tagged lines too
+            let line = match line.text {
+                Some(ref text) if conventional => parse_breaking_footer(text),
+                _ => None,
+            }
+            .unwrap_or(line);
+
             // If the line is categorized
-            if line.category.is_some() {
+            if line.category.is_some() || line.breaking {
                 // close the current active line
-                interesting |= self.record(current, conventions);
+                interesting |= self.record(current, commit, conventions);
 
                 // and reset it to a clean slate
                 current = Line::default();
                 current.scope = line.scope;
                 current.category = line.category;
+                current.breaking = line.breaking;
             }
 
             // If we don't have any text yet
@@ -186,7 +576,7 @@ impl<'a> RawReport<'a> {
         }
 
         // We've read all the lines, close the running current
-        interesting |= self.record(current, conventions);
+        interesting |= self.record(current, commit, conventions);
 
         // Update the report time
         if let Ok(time) = DateTime::parse_from_rfc2822(&commit.time) {
@@ -204,11 +594,21 @@ impl<'a> RawReport<'a> {
         interesting
     }
 
-    /// Record the current line into the report
-    fn record(&mut self, current: Line, conventions: &'a Conventions) -> bool {
+    /// Record the current line of the given commit into the report
+    fn record(&mut self, current: Line, commit: &Commit, conventions: &'a Conventions) -> bool {
+        // Breaking changes go to the breaking category, if one is configured
+        let category = match conventions.breaking {
+            Some(ref breaking) if current.breaking => Some(breaking.to_owned()),
+            _ => current.category,
+        };
+
+        // Remember the tags, they are recorded with the change
+        let scope_tag = current.scope.clone().unwrap_or_default();
+        let category_tag = category.clone().unwrap_or_default();
+
         // Get the titles and for the current scope and category
         let scope = conventions.scope_title(current.scope);
-        let category = conventions.category_title(current.category);
+        let category = conventions.category_title(category);
 
         // If the titles are missing, the user is not interested in these changes
         let interesting = category.is_some() && scope.is_some() && current.text.is_some();
@@ -218,13 +618,161 @@ impl<'a> RawReport<'a> {
             // Put it in its place
             self.slots
                 .entry(scope.unwrap())
-                .or_insert_with(HashMap::new)
+                .or_default()
                 .entry(category.unwrap())
-                .or_insert_with(Vec::new)
-                .push(current.text.unwrap());
+                .or_default()
+                .push(Change {
+                    text: current.text.unwrap(),
+                    sha: commit.sha.clone(),
+                    author: commit.author.clone(),
+                    time: commit.time.clone(),
+                    number: commit.number,
+                    references: commit.references.clone(),
+                    scope_tag,
+                    category_tag,
+                });
         }
 
         // Done
         interesting
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn range_start() {
+        use super::range_start;
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            range_start(&args(&["v0.1.0..HEAD"])),
+            Some(String::from("v0.1.0"))
+        );
+        assert_eq!(
+            range_start(&args(&["..v0.2.0"])),
+            Some(String::from("HEAD"))
+        );
+        assert_eq!(
+            range_start(&args(&["HEAD", "^v0.1.0"])),
+            Some(String::from("v0.1.0"))
+        );
+        assert_eq!(range_start(&args(&["v0.1.0...HEAD"])), None);
+        assert_eq!(range_start(&args(&["--reverse", "HEAD"])), None);
+        assert_eq!(range_start(&args(&["HEAD", "--", "../x..y"])), None);
+    }
+
+    #[test]
+    fn package_args() {
+        use super::package_args;
+        use input::Configuration;
+        let yml = "packages:\n  - {name: foo, paths: [crates/foo]}\n";
+        let config = Configuration::from_yaml(yml).unwrap();
+        let range = vec![String::from("foo-v0.1.0..HEAD")];
+        assert_eq!(package_args(range.clone(), &config).unwrap(), range);
+
+        let config = config.for_package("foo").unwrap();
+        assert_eq!(
+            package_args(range, &config).unwrap(),
+            [
+                "foo-v0.1.0..HEAD",
+                "--",
+                ":(top,glob)crates/foo",
+                ":(top,glob)crates/foo/**"
+            ]
+        );
+        let paths = vec![String::from("--")];
+        assert_eq!(package_args(paths, &config).unwrap().len(), 3);
+
+        // Git cannot limit the log to the package and the given paths at once
+        let paths = vec![String::from("--"), String::from("README.md")];
+        assert!(package_args(paths, &config).is_err());
+    }
+
+    #[test]
+    fn trailers() {
+        use super::RawReport;
+        use commit::Commit;
+        use input::Configuration;
+        let conventions = Configuration::from_yaml("preset: keepachangelog")
+            .unwrap()
+            .conventions;
+        let body = "- fix: bar\nbaz\nSigned-off-by: Jane <jane@x.org>\n  (again)\nFixes: #12";
+        let mut lines = vec!["abc", "Jane", "Sun, 22 Oct 2017 17:26:56 -0400", "foo"];
+        lines.extend(body.lines());
+        let commit = Commit::from_lines(lines.into_iter().map(String::from).collect());
+
+        // The trailers in the paragraph of a change are not part of its text
+        let mut report = RawReport::new();
+        assert!(report.add(&commit, &conventions));
+        let texts: Vec<&str> = report
+            .slots
+            .values()
+            .flat_map(|categories| categories.values())
+            .flat_map(|changes| changes.iter().map(|c| c.text.trim()))
+            .collect();
+        assert_eq!(texts, ["bar\nbaz"]);
+    }
+
+    #[test]
+    fn mailmap() {
+        use super::mailmap;
+        use git::GitBackend;
+        use std::path::Path;
+        use Result;
+
+        /// A backend whose mailmap upper cases everyone (like git, it fails on contacts without an
+        /// email)
+        struct Upper;
+        impl GitBackend for Upper {
+            fn root(&self) -> &Path {
+                Path::new(".")
+            }
+            fn in_git_repository(&self) -> Result<bool> {
+                Ok(true)
+            }
+            fn tags(&self) -> Result<Vec<String>> {
+                Ok(Vec::new())
+            }
+            fn reachable_tags(&self) -> Result<Vec<String>> {
+                self.tags()
+            }
+            fn commits_in_log(&self, _: &[String]) -> Result<String> {
+                Ok(String::new())
+            }
+            fn get_commit_message(&self, _: &str) -> Result<String> {
+                Ok(String::new())
+            }
+            fn get_remote_url(&self, _: &str) -> Result<Option<String>> {
+                Ok(None)
+            }
+            fn check_mailmap(&self, contacts: &[String]) -> Result<Vec<String>> {
+                match contacts.iter().find(|c| !c.contains('<')) {
+                    Some(c) => Err(format_err!("Invalid contact '{}'", c)),
+                    None => Ok(contacts.iter().map(|c| c.to_uppercase()).collect()),
+                }
+            }
+        }
+
+        // Contacts without an email are left alone, but do not stop the others from being mapped
+        let contacts = vec![
+            String::from("Jane <j@x.org>"),
+            String::from("Anonymous"),
+            String::from("Joe <joe@x.org>"),
+        ];
+        assert_eq!(
+            mailmap(contacts, &Upper),
+            ["JANE <J@X.ORG>", "Anonymous", "JOE <JOE@X.ORG>"]
+        );
+    }
+
+    #[test]
+    fn parse_contact() {
+        use super::parse_contact;
+        let contact = |n: &str, e: &str| (n.to_string(), e.to_string());
+        assert_eq!(
+            parse_contact("Jane Doe <jane@x.org>"),
+            contact("Jane Doe", "jane@x.org")
+        );
+        assert_eq!(parse_contact("Jane Doe"), contact("Jane Doe", ""));
+    }
+}
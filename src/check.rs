@@ -0,0 +1,293 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+// Revision range audits
+use changelog::{default_range, package_args, ChangeLog};
+use commit::{Commit, CommitList};
+use error::Error;
+use git::GitBackend;
+use input::Configuration;
+use lint::{lint_message, Problem};
+use std::collections::HashSet;
+use std::fmt;
+
+/// The result of checking the commits in a revision range.
+#[derive(Debug, Default, Serialize)]
+pub struct Audit {
+    /// The revision range
+    pub range: String,
+
+    /// The number of commits in the range
+    pub count: usize,
+
+    /// The commits that contribute nothing to the change log
+    pub untagged: Vec<AuditedCommit>,
+
+    /// The commits with tagged lines the change log drops
+    pub dropped: Vec<AuditedCommit>,
+
+    /// The policy violations (the check fails if there are any)
+    pub violations: Vec<String>,
+}
+
+/// A commit reported by an audit.
+#[derive(Debug, Default, Serialize)]
+pub struct AuditedCommit {
+    /// The SHA
+    pub sha: String,
+
+    /// The summary
+    pub summary: String,
+
+    /// Is it a merge commit?
+    pub merge: bool,
+
+    /// The problems with its tagged lines
+    pub problems: Vec<Problem>,
+}
+
+impl Audit {
+    /// Check the commits selected by the `git log` arguments against the configured policy, failing
+    /// if the configuration is invalid or the backend cannot read the log
+    pub fn from_log_with(
+        mut args: Vec<String>,
+        config: &Configuration,
+        backend: &dyn GitBackend,
+    ) -> Result<Self, Error> {
+        config.conventions.validate()?;
+
+        // Use the same default range as the change log
+        if args.is_empty() {
+            args.push(default_range(config, backend)?);
+        }
+
+        // Read the log once for the change log and the audit
+        let patterns = config.conventions.reference_patterns();
+        let commits: Vec<Commit> =
+            CommitList::try_from_backend(package_args(args.clone(), config)?, backend)?
+                .with_references(&patterns)?
+                .collect();
+
+        // The change log tells us which commits (and changes) made it (who is new does not matter)
+        let known = HashSet::new();
+        let picks = commits.iter().cloned();
+        let log = ChangeLog::from_log_commits(picks, args, &known, config, backend);
+        let picked: HashSet<&str> = log.commits.iter().map(|c| c.sha.as_str()).collect();
+
+        let mut audit = Audit {
+            range: log.range.clone(),
+            ..Audit::default()
+        };
+
+        let policy = &config.check;
+        for commit in &commits {
+            audit.count += 1;
+            let merge = commit.parents.len() > 1;
+            let name = format!("{} {}", short(&commit.sha), commit.summary);
+
+            // The tagged lines that were dropped (numbered as the message was written)
+            let problems = lint_message(&commit.raw, &config.conventions);
+            if !policy.allow_dropped {
+                for problem in &problems {
+                    audit.violations.push(format!("{}: {}", name, problem));
+                }
+            }
+
+            // The commits that contribute nothing
+            if !picked.contains(commit.sha.as_str()) {
+                if policy.require_tags && !merge {
+                    audit.violations.push(format!("{}: no tagged lines", name));
+                }
+                audit
+                    .untagged
+                    .push(AuditedCommit::new(commit, merge, Vec::new()));
+            }
+
+            // The changes that need others
+            let changes = changes(&log, commit);
+            for rule in &policy.requires {
+                let needed = changes
+                    .iter()
+                    .any(|&(_, category)| category == rule.category);
+                let found = changes.iter().any(|&(scope, _)| scope == rule.scope);
+                if needed && !found {
+                    audit.violations.push(format!(
+                        "{}: '{}' changes require a '{}' entry",
+                        name, rule.category, rule.scope
+                    ));
+                }
+            }
+
+            if !problems.is_empty() {
+                audit
+                    .dropped
+                    .push(AuditedCommit::new(commit, merge, problems));
+            }
+        }
+
+        Ok(audit)
+    }
+
+    /// Did the range pass the check?
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl AuditedCommit {
+    /// Construct from the commit
+    fn new(commit: &Commit, merge: bool, problems: Vec<Problem>) -> Self {
+        Self {
+            sha: commit.sha.clone(),
+            summary: commit.summary.clone(),
+            merge,
+            problems,
+        }
+    }
+}
+
+/// Get the scope and category tags of the changes the commit brought to the change log
+fn changes<'a>(log: &'a ChangeLog, commit: &Commit) -> Vec<(&'a str, &'a str)> {
+    log.scopes
+        .iter()
+        .flat_map(|s| s.categories.iter())
+        .flat_map(|c| c.changes.iter())
+        .filter(|c| c.sha == commit.sha)
+        .map(|c| (c.scope_tag.as_str(), c.category_tag.as_str()))
+        .collect()
+}
+
+/// Abbreviate the SHA
+fn short(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+impl fmt::Display for Audit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Checked {} ({} commits)", self.range, self.count)?;
+
+        if !self.untagged.is_empty() {
+            writeln!(f, "\nCommits without changes:")?;
+            for commit in &self.untagged {
+                let merge = if commit.merge { " (merge)" } else { "" };
+                writeln!(f, "  {} {}{}", short(&commit.sha), commit.summary, merge)?;
+            }
+        }
+
+        if !self.dropped.is_empty() {
+            writeln!(f, "\nCommits with dropped lines:")?;
+            for commit in &self.dropped {
+                writeln!(f, "  {} {}", short(&commit.sha), commit.summary)?;
+                for problem in &commit.problems {
+                    writeln!(f, "    {}", problem)?;
+                }
+            }
+        }
+
+        if !self.violations.is_empty() {
+            writeln!(f, "\nPolicy violations:")?;
+            for violation in &self.violations {
+                writeln!(f, "  {}", violation)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Audit;
+    use git::{format_record, GitBackend};
+    use input::{Configuration, Requirement};
+    use std::path::Path;
+    use Result;
+
+    /// A backend with a fixed log, newest first (the last commit is a merge)
+    struct FixedLog(Vec<String>);
+
+    impl GitBackend for FixedLog {
+        fn root(&self) -> &Path {
+            Path::new(".")
+        }
+        fn in_git_repository(&self) -> Result<bool> {
+            Ok(true)
+        }
+        fn tags(&self) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+        fn reachable_tags(&self) -> Result<Vec<String>> {
+            self.tags()
+        }
+        fn commits_in_log(&self, _: &[String]) -> Result<String> {
+            Ok(self.0.concat())
+        }
+        fn get_commit_message(&self, sha: &str) -> Result<String> {
+            Err(format_err!("No commit {}", sha))
+        }
+        fn get_remote_url(&self, _: &str) -> Result<Option<String>> {
+            Ok(None)
+        }
+        fn check_mailmap(&self, contacts: &[String]) -> Result<Vec<String>> {
+            Ok(contacts.to_vec())
+        }
+    }
+
+    #[test]
+    fn audit() {
+        let time = "Sun, 22 Oct 2017 17:26:56 -0400";
+        let commit = |sha: &str, parents: &str, message: &str| {
+            format_record(sha, parents, "Jane", "jane@x.org", time, message)
+        };
+        let git = FixedLog(vec![
+            commit(
+                "5555555eee",
+                "4444444ddd",
+                "Documented\n\n- break: foo\n- feature(doc): bar",
+            ),
+            commit("4444444ddd", "3333333ccc", "Break\n\n- break: foo"),
+            commit(
+                "3333333ccc",
+                "2222222bbb",
+                "Typo\nwith a long subject\n\n- fxi: foo",
+            ),
+            commit("2222222bbb", "1111111aaa", "Untagged"),
+            commit("1111111aaa", "0000000 0000001", "Merge branch 'x'"),
+        ]);
+        let range = vec![String::from("a..b")];
+
+        let mut config = Configuration::from_yaml(include_str!("assets/changelog.yml")).unwrap();
+        let audit = Audit::from_log_with(range.clone(), &config, &git).unwrap();
+        assert_eq!(audit.count, 5);
+        let untagged: Vec<&str> = audit.untagged.iter().map(|c| c.sha.as_str()).collect();
+        assert_eq!(untagged, vec!["1111111aaa", "2222222bbb", "3333333ccc"]);
+        assert!(audit.untagged[0].merge);
+        assert_eq!(audit.dropped.len(), 1);
+        let typo = "3333333 Typo with a long subject: line 4: unknown category 'fxi' \
+                    (did you mean 'fix'?)";
+        assert_eq!(audit.violations, vec![typo]);
+
+        // A stricter policy
+        config.check.allow_dropped = true;
+        config.check.require_tags = true;
+        config.check.requires.push(Requirement {
+            category: String::from("break"),
+            scope: String::from("doc"),
+        });
+        let audit = Audit::from_log_with(range.clone(), &config, &git).unwrap();
+        assert_eq!(
+            audit.violations,
+            vec![
+                "2222222 Untagged: no tagged lines",
+                "3333333 Typo with a long subject: no tagged lines",
+                "4444444 Break: 'break' changes require a 'doc' entry",
+            ]
+        );
+        assert!(!audit.passed());
+        assert!(audit.to_string().contains("Policy violations:"));
+
+        // A bad configuration fails the check
+        config.conventions.tag_pattern = Some(String::from("(unclosed"));
+        assert!(Audit::from_log_with(range, &config, &git).is_err());
+    }
+}
@@ -1,12 +1,16 @@
 // Copyright 2017-2018 by Aldrin J D'Souza.
 // Licensed under the MIT License <https://opensource.org/licenses/MIT>
 // Commit fetch and parsing logic
-use git;
+use error::Error;
+use git::{self, GitBackend, GitCommand};
+use input::ReferencePattern;
 use nom::{is_alphanumeric, IResult};
+use regex::Regex;
+use std::collections::BTreeMap;
 use std::{fmt, str};
 
 /// A single commit
-#[derive(Debug, Default, Serialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Eq, PartialEq)]
 pub struct Commit {
     /// The SHA
     pub sha: String,
@@ -14,17 +18,34 @@ pub struct Commit {
     /// The author
     pub author: String,
 
+    /// The author email
+    pub email: String,
+
     /// The timestamp
     pub time: String,
 
     /// The summary
     pub summary: String,
 
-    /// The change number
-    pub number: Option<u32>,
+    /// The change number (the last pull or merge request reference)
+    pub number: Option<u64>,
+
+    /// The references (e.g. `(#123)` or `(!456)`) found on the subject, in order
+    pub references: Vec<Reference>,
 
     /// The message
     pub message: String,
+
+    /// The trailers at the end of the message (e.g. `Co-authored-by`, `Fixes`), keyed by their lowercase
+    /// names. Trailer lines are not part of the message.
+    pub trailers: BTreeMap<String, Vec<String>>,
+
+    /// The parent SHAs (a merge commit has more than one)
+    pub parents: Vec<String>,
+
+    /// The message as it was written (the subject and the body, with the trailers)
+    #[serde(skip)]
+    pub raw: String,
 }
 
 /// A list of commit revisions
@@ -32,8 +53,35 @@ pub struct CommitList {
     /// The log command
     input: String,
 
-    /// The commits in the log
-    commits: Vec<String>,
+    /// The unparsed commit records in the log
+    log: String,
+
+    /// The reference patterns the subjects are parsed with
+    references: Vec<(ReferenceKind, Regex)>,
+}
+
+/// A reference to a pull request, merge request or issue on the commit subject
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+pub struct Reference {
+    /// What is referenced
+    pub kind: ReferenceKind,
+
+    /// The referenced number
+    pub number: u64,
+}
+
+/// The kinds of references
+#[serde(rename_all = "lowercase")]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+pub enum ReferenceKind {
+    /// A pull request (e.g. `(#123)` on GitHub)
+    Pr,
+
+    /// A merge request (e.g. `(!456)` on GitLab)
+    Mr,
+
+    /// An issue
+    Issue,
 }
 
 /// The commit message
@@ -50,39 +98,117 @@ pub struct Line {
 
     /// The text
     pub text: Option<String>,
+
+    /// Marked as a breaking change (Conventional Commits only)
+    pub breaking: bool,
 }
 
 impl<T: AsRef<str>> From<T> for Commit {
     /// Construct a commit from the revision
     fn from(input: T) -> Self {
         let revision = input.as_ref();
-        match git::get_commit_message(revision) {
-            Ok(lines) => Commit::from_lines(lines),
-            Err(why) => {
-                error!("Commit {} will be skipped (Reason: {})", revision, why);
-                Commit::default()
-            }
-        }
+        Self::try_from(revision).unwrap_or_else(|why| {
+            error!("Commit {} will be skipped (Reason: {})", revision, why);
+            Commit::default()
+        })
     }
 }
 
 impl Commit {
-    pub fn from_lines(mut lines: Vec<String>) -> Self {
+    /// Construct a commit from the revision, failing if git cannot read it
+    pub fn try_from<T: AsRef<str>>(revision: T) -> ::std::result::Result<Self, Error> {
+        let record = GitCommand::default()
+            .get_commit_message(revision.as_ref())
+            .map_err(Error::from_git)?;
+        Ok(Commit::from_record(&record, &default_references()))
+    }
+
+    /// Construct a commit from a single record in the `git log` stream
+    fn from_record(record: &str, references: &[(ReferenceKind, Regex)]) -> Self {
+        // Drop the record separator and the trailing newlines git puts between records
+        let record = record
+            .trim_start_matches(git::RECORD_SEPARATOR)
+            .trim_end_matches('\n');
+
+        // The first five fields are single lines, the message is whatever remains
+        let mut fields = record.splitn(6, git::FIELD_SEPARATOR);
+        let mut field = || fields.next().unwrap_or_default();
+        let (sha, parents, author, email, time) = (field(), field(), field(), field(), field());
+        let raw = field();
+
+        // Like `%s` and `%b`, the subject is the first paragraph (on a line) and the body the rest
+        let mut message = raw.lines().skip_while(|l| l.trim().is_empty());
+        let subject: Vec<&str> = message
+            .by_ref()
+            .take_while(|l| !l.trim().is_empty())
+            .map(str::trim)
+            .collect();
+        let mut lines = vec![
+            sha.to_owned(),
+            author.to_owned(),
+            time.to_owned(),
+            subject.join(" "),
+        ];
+        lines.extend(
+            message
+                .skip_while(|l| l.trim().is_empty())
+                .map(String::from),
+        );
+
+        let mut commit = Commit::parse_lines(lines, references);
+        commit.email = email.to_owned();
+        commit.parents = parents.split_whitespace().map(String::from).collect();
+        commit.raw = raw.to_owned();
+        commit
+    }
+
+    /// Construct a commit from its sha, author, time, subject and message lines
+    pub fn from_lines(lines: Vec<String>) -> Self {
+        Self::parse_lines(lines, &default_references())
+    }
+
+    /// Construct a commit from its lines, finding the subject references with the given patterns
+    fn parse_lines(mut lines: Vec<String>, patterns: &[(ReferenceKind, Regex)]) -> Self {
         let sha = lines.remove(0);
         let author = lines.remove(0);
         let time = lines.remove(0);
         let subject = lines.remove(0);
-        let number = parse_number(&subject);
-        let summary = parse_subject(&subject);
+        let references = parse_references(&subject, patterns);
+        let number = references
+            .iter()
+            .filter(|r| r.kind != ReferenceKind::Issue)
+            .map(|r| r.number)
+            .next_back();
+        let summary = parse_subject(&subject, patterns);
+        let raw = if lines.is_empty() {
+            subject
+        } else {
+            format!("{}\n\n{}", subject, lines.join("\n"))
+        };
+        let trailers = parse_trailers(&mut lines);
         let message = lines.join("\n");
         Self {
             sha,
             author,
             time,
             number,
+            references,
             summary,
             message,
+            trailers,
+            email: String::new(),
+            parents: Vec::new(),
+            raw,
+        }
+    }
+
+    /// Get the contacts (`Name <email>`) of the author and the co-authors of this commit
+    pub fn contacts(&self) -> Vec<String> {
+        let mut contacts = vec![format!("{} <{}>", self.author, self.email)];
+        if let Some(co_authors) = self.trailers.get("co-authored-by") {
+            contacts.extend(co_authors.iter().cloned());
         }
+        contacts
     }
 }
 
@@ -96,25 +222,69 @@ impl<'a> From<&'a str> for CommitList {
 impl From<Vec<String>> for CommitList {
     /// Generate a commit list from the list of strings, interpreting them as `git log` arguments.
     fn from(git_log_args: Vec<String>) -> Self {
+        Self::from_backend(git_log_args, &GitCommand::default())
+    }
+}
+
+impl CommitList {
+    /// Generate a commit list from the `git log` arguments, reading the log with the given backend.
+    pub fn from_backend(git_log_args: Vec<String>, backend: &dyn GitBackend) -> Self {
+        let input = git_log_args.join(" ");
+        Self::try_from_backend(git_log_args, backend).unwrap_or_else(|why| {
+            error!("Invalid log input {} (Reason: {})", input, why);
+            CommitList {
+                log: String::new(),
+                input,
+                references: default_references(),
+            }
+        })
+    }
+
+    /// Generate a commit list from the `git log` arguments, failing if git cannot read the log.
+    pub fn try_from(git_log_args: Vec<String>) -> ::std::result::Result<Self, Error> {
+        Self::try_from_backend(git_log_args, &GitCommand::default())
+    }
+
+    /// Generate a commit list from the `git log` arguments with the given backend, failing if it cannot
+    /// read the log.
+    pub fn try_from_backend(
+        git_log_args: Vec<String>,
+        backend: &dyn GitBackend,
+    ) -> ::std::result::Result<Self, Error> {
         // Record the log input
         let input = git_log_args.join(" ");
 
-        // Get the commits that `git log` would have returned
-        let commits = match git::commits_in_log(&git_log_args) {
-            Ok(commits) => commits,
-            Err(why) => {
-                error!("Invalid log input {} (Reason: {})", input, why);
-                vec![]
-            }
-        };
-        CommitList { commits, input }
+        // Get the commit records that `git log` would have returned
+        let log = backend
+            .commits_in_log(&git_log_args)
+            .map_err(Error::from_git)?;
+        let references = default_references();
+        Ok(CommitList {
+            log,
+            input,
+            references,
+        })
+    }
+
+    /// Use the given patterns (instead of the built-in ones) to find the references on commit
+    /// subjects, failing if any of them is not a valid regular expression
+    pub fn with_references(
+        mut self,
+        patterns: &[ReferencePattern],
+    ) -> ::std::result::Result<Self, Error> {
+        self.references = compile(patterns)?;
+        Ok(self)
     }
 }
 
 impl Iterator for CommitList {
     type Item = Commit;
     fn next(&mut self) -> Option<Self::Item> {
-        self.commits.pop().map(Commit::from)
+        // Records are parsed from the end of the stream, one at a time
+        self.log
+            .rfind(git::RECORD_SEPARATOR)
+            .map(|start| self.log.split_off(start))
+            .map(|record| Commit::from_record(&record, &self.references))
     }
 }
 
@@ -145,37 +315,169 @@ impl fmt::Display for Commit {
 
 impl fmt::Display for CommitList {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} ({} commits)", self.input, self.commits.len())
+        let count = self.log.matches(git::RECORD_SEPARATOR).count();
+        write!(f, "{} ({} commits)", self.input, count)
     }
 }
 
-/// Parse the commit subject removing the numbers tags.
-fn parse_subject(line: &str) -> String {
-    // Find the first number opener on the commit subject
-    let first_open = line.find("(#").unwrap_or_else(|| line.len());
+/// Get the contacts of the authors and co-authors of the commit records in the log
+pub fn log_contacts(log: &str) -> Vec<String> {
+    log.split(git::RECORD_SEPARATOR)
+        .skip(1)
+        .flat_map(|record| Commit::from_record(record, &[]).contacts())
+        .collect()
+}
 
-    // Everything up to the first number opener is the subject
-    String::from(line.get(0..first_open).unwrap_or(line).trim())
+/// Compile the reference patterns, failing on the first invalid one.
+fn compile(
+    patterns: &[ReferencePattern],
+) -> ::std::result::Result<Vec<(ReferenceKind, Regex)>, Error> {
+    patterns
+        .iter()
+        .map(|p| match Regex::new(&p.pattern) {
+            Ok(regex) => Ok((p.kind, regex)),
+            Err(why) => Err(Error::InvalidConfig(format!(
+                "Invalid reference pattern '{}' (Reason: {})",
+                p.pattern, why
+            ))),
+        })
+        .collect()
 }
 
-/// Parse the commit number
-fn parse_number(line: &str) -> Option<u32> {
-    // The commit number is the last number on the subject
-    let last_open = line.rfind("(#").map(|x| x + "(#".len());
+/// The built-in reference patterns, compiled once (per thread).
+fn default_references() -> Vec<(ReferenceKind, Regex)> {
+    thread_local! {
+        static DEFAULTS: Vec<(ReferenceKind, Regex)> =
+            compile(&ReferencePattern::defaults()).expect("valid default references");
+    }
+    DEFAULTS.with(Clone::clone)
+}
 
-    // The last number opener
-    let last_close = line.rfind(')');
+/// Parse the commit subject removing the references.
+fn parse_subject(line: &str, patterns: &[(ReferenceKind, Regex)]) -> String {
+    let mut subject = String::from(line);
+    for (_, regex) in patterns {
+        // Drop every reference this pattern finds, along with the space before it
+        let mut kept = String::new();
+        let mut last = 0;
+        for found in regex.find_iter(&subject) {
+            kept.push_str(subject[last..found.start()].trim_end());
+            last = found.end();
+        }
+        kept.push_str(&subject[last..]);
+        subject = kept;
+    }
+    String::from(subject.trim())
+}
 
-    // If one was found, parse it to a number
-    if let (Some(start), Some(end)) = (last_open, last_close) {
-        line.get(start..end).map(|s| s.parse().ok()).unwrap_or(None)
-    } else {
-        None
+/// Parse the references on the commit subject, in the order they appear
+fn parse_references(line: &str, patterns: &[(ReferenceKind, Regex)]) -> Vec<Reference> {
+    let mut found = Vec::new();
+    for &(kind, ref regex) in patterns {
+        for captures in regex.captures_iter(line) {
+            // The number is the first group (or the whole match, if there are no groups)
+            let group = captures.get(1).or_else(|| captures.get(0));
+            let number = group.and_then(|m| m.as_str().parse().ok());
+            if let (Some(number), Some(at)) = (number, captures.get(0)) {
+                found.push((at.start(), Reference { kind, number }));
+            }
+        }
     }
+    found.sort_by_key(|&(at, _)| at);
+    found.into_iter().map(|(_, reference)| reference).collect()
+}
+
+/// Split the trailers off the end of the message lines.
+///
+/// Like `git interpret-trailers`, the trailers are the last paragraph of the message, provided all its
+/// lines are `Key: value` pairs (or indented continuations of one).
+fn parse_trailers(lines: &mut Vec<String>) -> BTreeMap<String, Vec<String>> {
+    let mut trailers = BTreeMap::new();
+
+    // Find the last paragraph, ignoring trailing blank lines
+    let blank = |l: &String| l.trim().is_empty();
+    let end = lines.iter().rposition(|l| !blank(l)).map_or(0, |i| i + 1);
+    let start = lines[..end].iter().rposition(blank).map_or(0, |i| i + 1);
+
+    // Parse each line of the paragraph, giving up on the first one that doesn't fit
+    let mut parsed: Vec<(String, String)> = Vec::new();
+    for line in &lines[start..end] {
+        if line.starts_with(char::is_whitespace) && !parsed.is_empty() {
+            if let Some(last) = parsed.last_mut() {
+                last.1.push(' ');
+                last.1.push_str(line.trim());
+            }
+        } else if let Some(t) = parse_trailer(line) {
+            parsed.push(t);
+        } else {
+            return trailers;
+        }
+    }
+
+    // Take the trailers out of the message
+    if !parsed.is_empty() {
+        lines.truncate(start);
+        while matches!(lines.last(), Some(line) if blank(line)) {
+            lines.pop();
+        }
+        for (key, value) in parsed {
+            trailers.entry(key).or_insert_with(Vec::new).push(value);
+        }
+    }
+
+    trailers
+}
+
+/// Count the trailer lines (and their continuations) at the end of the message, like the
+/// `Signed-off-by` lines under a tagged line in the same paragraph.
+pub fn trailing_trailers(message: &str) -> usize {
+    let (mut count, mut continued) = (0, 0);
+    for line in message.trim_end().lines().rev() {
+        if parse_trailer(line).is_some() {
+            count += continued + 1;
+            continued = 0;
+        } else if line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+            continued += 1;
+        } else {
+            break;
+        }
+    }
+    count
+}
+
+/// Parse a single trailer line
+fn parse_trailer(line: &str) -> Option<(String, String)> {
+    match trailer(line) {
+        // Tagged lines and breaking change footers are changes, not trailers
+        IResult::Done(_, (ref key, _)) if key.starts_with('-') || key == "breaking-change" => None,
+        IResult::Done(_, t) => Some(t),
+        _ => None,
+    }
+}
+
+/// Parse a Conventional Commits subject line (e.g. `feat(api)!: add X`)
+pub fn parse_conventional_subject(subject: &str) -> Option<Line> {
+    match conventional_subject(subject) {
+        IResult::Done(_, l) => Some(l),
+        _ => None,
+    }
+}
+
+/// Parse a Conventional Commits breaking change footer (e.g. `BREAKING CHANGE: text`)
+pub fn parse_breaking_footer(line: &str) -> Option<Line> {
+    ["BREAKING CHANGE:", "BREAKING-CHANGE:"]
+        .iter()
+        .find(|footer| line.starts_with(*footer))
+        .map(|footer| Line {
+            scope: None,
+            category: None,
+            text: Some(String::from(&line[footer.len()..])),
+            breaking: true,
+        })
 }
 
 /// Parse an individual message line
-fn parse_line(line: &str) -> Line {
+pub fn parse_line(line: &str) -> Line {
     // Parse the tags in the line
     match tagged_change(line) {
         // If parser succeeded, we have our line
@@ -195,6 +497,20 @@ alt!(with_category
     | with_text
 ));
 
+// A Conventional Commits subject, i.e. a category, an optional scope and breaking marker, and a change.
+named!(conventional_subject<&str, Line>,
+do_parse!(
+    category: tagname >>
+        scope: opt!(delimited!(tag!("("), tagname, tag!(")"))) >>
+        breaking: opt!(tag!("!")) >>
+        tag!(":") >> text: whatever >>
+        (Line{
+            scope,
+            category: Some(category),
+            text: Some(text),
+            breaking: breaking.is_some()
+        })));
+
 // A line that has just a simple change (no tags).
 named!(with_text<&str, Line>,
 do_parse!(opt!(tag!("-")) >>
@@ -202,7 +518,8 @@ do_parse!(opt!(tag!("-")) >>
           (Line{
               scope: None,
               category: None,
-              text: Some(text)
+              text: Some(text),
+              breaking: false
           })));
 
 // A line that has just a category
@@ -213,7 +530,8 @@ do_parse!(
         (Line{
             scope: None,
             category: Some(category),
-            text: None
+            text: None,
+            breaking: false
         })));
 
 // A line that has just a category and scope, but no change text
@@ -225,7 +543,8 @@ do_parse!(
         (Line{
             scope: Some(scope),
             category: Some(category),
-            text: None
+            text: None,
+            breaking: false
         })));
 
 // A line that has a category and a change text, but no scope.
@@ -236,7 +555,8 @@ do_parse!(
         (Line{
             scope: None,
             category: Some(category),
-            text: Some(text)
+            text: Some(text),
+            breaking: false
         })));
 
 // A line that has everything, i.e. category, scope and a change.
@@ -248,9 +568,21 @@ do_parse!(
         (Line{
             scope: Some(scope),
             category: Some(category),
-            text: Some(text)
+            text: Some(text),
+            breaking: false
          })));
 
+// A trailer line, i.e. a key and a value.
+named!(trailer<&str, (String, String)>,
+do_parse!(
+    key: trailer_key >>
+        tag!(": ") >> value: whatever >>
+        (key, value.trim().to_string())));
+
+// Consume an acceptable trailer key and return a lowercase String
+named!(trailer_key<&str, String>,
+       map!(take_while1_s!(|c| is_alphanumeric(c as u8) || c == '-'), str::to_lowercase));
+
 // Consume whatever is left and return a String
 named!(whatever<&str, String>,
        map!(take_while1_s!(|_| true), String::from));
@@ -276,6 +608,17 @@ mod tests {
     fn negative() {
         assert!(super::Commit::from("no-such-commit").summary.is_empty());
         assert_eq!(super::CommitList::from("bad-range").into_iter().count(), 0);
+
+        // The fallible constructors report the failures
+        use error::Error;
+        match super::CommitList::try_from(vec![String::from("bad-range")]) {
+            Err(Error::BadRevision(why)) => assert!(why.contains("bad-range")),
+            other => panic!("Unexpected {:?}", other.map(|l| l.to_string())),
+        }
+        match super::Commit::try_from("no-such-commit") {
+            Err(Error::BadRevision(_)) => {}
+            other => panic!("Unexpected {:?}", other),
+        }
     }
 
     #[test]
@@ -285,24 +628,155 @@ mod tests {
         assert_eq!(reference.into_iter().count(), 17);
     }
 
+    #[test]
+    fn commit_from_record() {
+        use super::{default_references, Commit};
+        let patterns = default_references();
+        let record = "\x1eabc\x00p1 p2\x00Jane Doe\x00jane@x.org\x00\
+                      Sun, 22 Oct 2017 17:26:56 -0400\x00foo (#12)\n\n- fix: bar\nbaz\n\n";
+        let commit = Commit::from_record(record, &patterns);
+        assert_eq!(commit.sha, "abc");
+        assert_eq!(commit.parents, ["p1", "p2"]);
+        assert_eq!(commit.author, "Jane Doe");
+        assert_eq!(commit.email, "jane@x.org");
+        assert_eq!(commit.time, "Sun, 22 Oct 2017 17:26:56 -0400");
+        assert_eq!(commit.summary, "foo");
+        assert_eq!(commit.number, Some(12));
+        assert_eq!(commit.message, "- fix: bar\nbaz");
+        assert_eq!(commit.raw, "foo (#12)\n\n- fix: bar\nbaz");
+
+        // A record with an empty body
+        let commit = Commit::from_record("\x1eabc\x00\x00Jane Doe\x00\x00\x00foo", &patterns);
+        assert_eq!(commit.summary, "foo");
+        assert!(commit.message.is_empty());
+        assert!(commit.parents.is_empty());
+
+        // Like `%s`, the subject is the whole first paragraph
+        let commit = Commit::from_record("\x1eabc\x00\x00J\x00\x00\x00foo\nbar\n\nbaz", &patterns);
+        assert_eq!(commit.summary, "foo bar");
+        assert_eq!(commit.message, "baz");
+    }
+
+    #[test]
+    fn commit_trailers() {
+        use super::Commit;
+        let commit = |body: &str| {
+            let mut lines = vec!["abc", "Jane", "Sun, 22 Oct 2017 17:26:56 -0400", "foo"];
+            lines.extend(body.lines());
+            Commit::from_lines(lines.into_iter().map(String::from).collect())
+        };
+
+        let c = commit("- fix: bar\nbaz\n\nCo-authored-by: Joe <joe@x.org>\nFixes: #12\n  and #13\nfixes: #14\n");
+        assert_eq!(c.message, "- fix: bar\nbaz");
+        assert_eq!(c.trailers["co-authored-by"], vec!["Joe <joe@x.org>"]);
+        assert_eq!(c.trailers["fixes"], vec!["#12 and #13", "#14"]);
+
+        // Only trailers in the message
+        let c = commit("Signed-off-by: Jane <jane@x.org>");
+        assert!(c.message.is_empty());
+        assert_eq!(c.trailers.len(), 1);
+
+        // Not trailers: mixed paragraphs, tagged lines, breaking changes and URLs
+        for body in &[
+            "- fix: bar\n\nFixes: #12\nnot a trailer",
+            "-fix: bar",
+            "BREAKING-CHANGE: bar",
+            "See https://example.com",
+            "  Fixes: #12",
+        ] {
+            let c = commit(body);
+            assert!(c.trailers.is_empty(), "{}", body);
+            assert_eq!(&c.message, body);
+        }
+    }
+
     #[test]
     fn commit_parse_summary() {
-        use super::{parse_number, parse_subject};
+        use super::{compile, default_references, parse_references, parse_subject};
+        use super::{Reference, ReferenceKind};
+        use input::ReferencePattern;
+        let patterns = default_references();
+        let numbers = |line: &str, patterns: &[(ReferenceKind, _)]| -> Vec<u64> {
+            parse_references(line, patterns)
+                .into_iter()
+                .map(|r| r.number)
+                .collect()
+        };
 
         // most common - simple PR merge
         let message = "foo bar (#123)";
-        assert_eq!(parse_subject(message), "foo bar");
-        assert_eq!(parse_number(message), Some(123));
+        assert_eq!(parse_subject(message, &patterns), "foo bar");
+        assert_eq!(numbers(message, &patterns), vec![123]);
 
         // not a PR merge
         let message = "foo bar ()()";
-        assert_eq!(parse_subject(message), message);
-        assert_eq!(parse_number(message), None);
+        assert_eq!(parse_subject(message, &patterns), message);
+        assert!(numbers(message, &patterns).is_empty());
 
         // cherry-picked multi-PR commits
         let message = "foo bar #123 (#101)(#103)";
-        assert_eq!(parse_subject(message), "foo bar #123");
-        assert_eq!(parse_number(message), Some(103));
+        assert_eq!(parse_subject(message, &patterns), "foo bar #123");
+        assert_eq!(numbers(message, &patterns), vec![101, 103]);
+
+        // GitLab merge requests, mixed with PRs
+        let message = "foo (!456) bar (#7)";
+        assert_eq!(parse_subject(message, &patterns), "foo bar");
+        let references = parse_references(message, &patterns);
+        assert_eq!(
+            references,
+            vec![
+                Reference {
+                    kind: ReferenceKind::Mr,
+                    number: 456
+                },
+                Reference {
+                    kind: ReferenceKind::Pr,
+                    number: 7
+                },
+            ]
+        );
+
+        // Text after the references stays, and only the space before each reference goes
+        let message = "foo  bar (#7) - baz (!8)";
+        assert_eq!(parse_subject(message, &patterns), "foo  bar - baz");
+
+        // Project specific patterns
+        let pattern = |pattern: &str| ReferencePattern {
+            kind: ReferenceKind::Issue,
+            pattern: String::from(pattern),
+        };
+        let patterns = compile(&[pattern(r"\[JIRA-(\d+)\]")]).unwrap();
+        let message = "[JIRA-12] foo (#1)";
+        assert_eq!(parse_subject(message, &patterns), "foo (#1)");
+        assert_eq!(numbers(message, &patterns), vec![12]);
+
+        // Invalid patterns are configuration errors
+        assert!(compile(&[pattern(r"\[JIRA-(\d+")]).is_err());
+    }
+
+    #[test]
+    fn commit_parse_conventional() {
+        use commit::{parse_breaking_footer, parse_conventional_subject};
+
+        let line = parse_conventional_subject("feat(API)!: add X").unwrap();
+        assert_eq!(Some(String::from("api")), line.scope);
+        assert_eq!(Some(String::from("feat")), line.category);
+        assert_eq!(Some(String::from(" add X")), line.text);
+        assert!(line.breaking);
+
+        let line = parse_conventional_subject("fix: foo bar").unwrap();
+        assert_eq!(None, line.scope);
+        assert_eq!(Some(String::from("fix")), line.category);
+        assert!(!line.breaking);
+
+        assert!(parse_conventional_subject("Merge branch 'foo'").is_none());
+        assert!(parse_conventional_subject("fix:").is_none());
+
+        let line = parse_breaking_footer("BREAKING CHANGE: foo").unwrap();
+        assert_eq!(Some(String::from(" foo")), line.text);
+        assert!(line.breaking);
+        assert!(parse_breaking_footer("BREAKING-CHANGE: foo").is_some());
+        assert!(parse_breaking_footer("Breaking change: foo").is_none());
     }
 
     #[test]
@@ -0,0 +1,109 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+// The library errors
+use std::error;
+use std::fmt;
+
+/// The errors the library reports.
+///
+/// The fallible constructors (e.g. [`ChangeLog::try_from_log`](struct.ChangeLog.html#method.try_from_log))
+/// return these directly. Functions that return the crate [`Result`](type.Result.html) carry them
+/// inside, so `err.downcast_ref::<changelog::Error>()` tells them apart.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The directory is not in a git repository
+    NotARepository(String),
+
+    /// A revision (or range) git does not know
+    BadRevision(String),
+
+    /// Any other git failure
+    GitFailed(String),
+
+    /// The configuration is invalid
+    InvalidConfig(String),
+
+    /// The template is invalid
+    TemplateError(String),
+
+    /// The output cannot be rendered
+    RenderError(String),
+}
+
+impl Error {
+    /// Turn an error from a git backend into a library error (unless it already is one)
+    pub fn from_git(why: ::anyhow::Error) -> Self {
+        match why.downcast::<Error>() {
+            Ok(error) => error,
+            Err(why) => Error::GitFailed(why.to_string()),
+        }
+    }
+}
+
+/// Classify the error git reported on the standard error
+pub fn git_error(stderr: &str) -> Error {
+    let message = stderr.trim().to_owned();
+    let lower = message.to_lowercase();
+    let bad_revision = [
+        "unknown revision",
+        "bad revision",
+        "invalid revision range",
+        "bad object",
+        "ambiguous argument",
+    ];
+    if lower.contains("not a git repository") {
+        Error::NotARepository(message)
+    } else if bad_revision.iter().any(|b| lower.contains(b)) {
+        Error::BadRevision(message)
+    } else {
+        Error::GitFailed(message)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NotARepository(ref why) => write!(f, "Not a git repository ({})", why),
+            Error::BadRevision(ref why) => write!(f, "Bad revision ({})", why),
+            Error::GitFailed(ref why) => write!(f, "Git failed ({})", why),
+            Error::InvalidConfig(ref why)
+            | Error::TemplateError(ref why)
+            | Error::RenderError(ref why) => write!(f, "{}", why),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::{git_error, Error};
+
+    #[test]
+    fn git_errors() {
+        let not_a_repo = "fatal: not a git repository (or any of the parent directories): .git";
+        assert_eq!(
+            git_error(not_a_repo),
+            Error::NotARepository(String::from(not_a_repo))
+        );
+        let bad = "fatal: ambiguous argument 'v9..HEAD': unknown revision or path not in the working tree.";
+        assert_eq!(git_error(bad), Error::BadRevision(String::from(bad)));
+        assert_eq!(
+            git_error("fatal: oops\n"),
+            Error::GitFailed(String::from("fatal: oops"))
+        );
+
+        // Library errors survive the trip through the crate `Result`
+        let wrapped = ::anyhow::Error::from(Error::BadRevision(String::from("x")));
+        assert_eq!(
+            Error::from_git(wrapped),
+            Error::BadRevision(String::from("x"))
+        );
+        let other = format_err!("spawn failed");
+        assert_eq!(
+            Error::from_git(other),
+            Error::GitFailed(String::from("spawn failed"))
+        );
+    }
+}
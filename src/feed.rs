@@ -0,0 +1,218 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+// Atom feeds of releases
+use changelog::ChangeLog;
+use chrono::{DateTime, FixedOffset, Utc};
+use output::escape;
+use std::fmt::Write;
+
+/// The feed id when there is no remote to name it
+const FEED_URN: &str = "urn:git-changelog:releases";
+
+/// Render the releases (newest first) as an Atom feed, one entry per release.
+///
+/// Entries are dated by the release tag (or the last commit in the release, the ones with neither
+/// are left out), hold the categorized changes as HTML and link to the remote (the comparison of
+/// the release range, if there is one).
+pub fn atom(releases: &[(&str, &ChangeLog)]) -> String {
+    let entries: Vec<(&str, &ChangeLog, DateTime<FixedOffset>)> = releases
+        .iter()
+        .filter_map(|&(title, clog)| updated(clog).map(|time| (title, clog, time)))
+        .collect();
+
+    // The remote of the newest release names the feed
+    let remote = releases
+        .iter()
+        .filter_map(|r| r.1.remote_url.as_ref())
+        .next();
+    let id = remote.map_or(FEED_URN, String::as_str);
+    let name = remote
+        .and_then(|url| url.rsplit('/').next())
+        .unwrap_or("Project");
+    let last = entries
+        .iter()
+        .map(|e| e.2)
+        .max()
+        .unwrap_or_else(|| DateTime::<Utc>::default().into());
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&element("  ", "title", &format!("{} releases", name)));
+    xml.push_str(&element("  ", "id", id));
+    xml.push_str(&element("  ", "updated", &last.to_rfc3339()));
+    if let Some(url) = remote {
+        xml.push_str(&format!("  <link href=\"{}\"/>\n", escape(url)));
+    }
+    xml.push_str("  <author>\n");
+    xml.push_str(&element("    ", "name", name));
+    xml.push_str("  </author>\n");
+
+    for (title, clog, time) in entries {
+        let link = clog.compare_url.as_ref().or(clog.remote_url.as_ref());
+        let entry_id = match clog.compare_url {
+            Some(ref url) => url.clone(),
+            None => format!("{}#{}", id, fragment(title)),
+        };
+        xml.push_str("  <entry>\n");
+        xml.push_str(&element("    ", "title", title));
+        xml.push_str(&element("    ", "id", &entry_id));
+        xml.push_str(&element("    ", "updated", &time.to_rfc3339()));
+        if let Some(url) = link {
+            xml.push_str(&format!("    <link href=\"{}\"/>\n", escape(url)));
+        }
+        xml.push_str(&format!(
+            "    <content type=\"html\">{}</content>\n",
+            escape(&html(clog))
+        ));
+        xml.push_str("  </entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// The time the release was tagged, or of the last commit in the change log
+fn updated(clog: &ChangeLog) -> Option<DateTime<FixedOffset>> {
+    let parse = |time: &String| DateTime::parse_from_rfc2822(time).ok();
+    clog.tag_time
+        .as_ref()
+        .and_then(parse)
+        .or_else(|| clog.commits.iter().filter_map(|c| parse(&c.time)).max())
+}
+
+/// Render the categorized changes as HTML
+fn html(clog: &ChangeLog) -> String {
+    let mut html = String::new();
+    for scope in &clog.scopes {
+        if !scope.title.is_empty() {
+            html.push_str(&format!("<h2>{}</h2>", escape(&scope.title)));
+        }
+        for category in &scope.categories {
+            if !category.title.is_empty() {
+                html.push_str(&format!("<h3>{}</h3>", escape(&category.title)));
+            }
+            html.push_str("<ul>");
+            for change in &category.changes {
+                let text: Vec<&str> = change.text.split_whitespace().collect();
+                html.push_str(&format!("<li>{}", escape(&text.join(" "))));
+                if let (Some(url), Some(sha)) = (clog.commit_url.as_ref(), change.sha.get(..7)) {
+                    html.push_str(&format!(
+                        " (<a href=\"{}{}\">{}</a>)",
+                        escape(url),
+                        escape(&change.sha),
+                        escape(sha)
+                    ));
+                }
+                html.push_str("</li>");
+            }
+            html.push_str("</ul>");
+        }
+    }
+    html
+}
+
+/// An element with the given text on its own line
+fn element(indent: &str, name: &str, text: &str) -> String {
+    format!("{}<{}>{}</{}>\n", indent, name, escape(text), name)
+}
+
+/// Percent-encode the text for a URL fragment (everything but the unreserved characters)
+fn fragment(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{:02X}", byte);
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::atom;
+    use changelog::ChangeLog;
+    use commit::Commit;
+    use input::Configuration;
+
+    #[test]
+    fn atom_feed() {
+        let config = Configuration::from_yaml(include_str!("assets/changelog.yml")).unwrap();
+        let release = |sha: &str, time: &str, body: &str| {
+            let lines = vec![sha, "Jane", time, "Subject", body];
+            let commit = Commit::from_lines(lines.into_iter().map(String::from).collect());
+            let mut clog = ChangeLog::from(vec![commit].into_iter(), &config);
+            clog.remote_url = Some(String::from("https://github.com/a/b&c"));
+            clog.commit_url = Some(String::from("https://github.com/a/b&c/commit/"));
+            clog
+        };
+        let v2 = release(
+            "2222222bbbbbbb",
+            "Mon, 23 Oct 2017 09:05:00 +0200",
+            "- fix: Handle <empty> \"responses\" & 'nulls'",
+        );
+        let mut v1 = release(
+            "1111111aaaaaaa",
+            "Sun, 22 Oct 2017 17:26:56 -0400",
+            "- feature: Filters",
+        );
+        v1.compare_url = Some(String::from("https://github.com/a/b&c/compare/v0...v1"));
+        v1.tag_time = Some(String::from("Tue, 24 Oct 2017 08:00:00 +0000"));
+        let xml = atom(&[("v2 <rc>", &v2), ("v1", &v1)]);
+
+        // It parses, and the text comes back intact
+        let doc = ::roxmltree::Document::parse(&xml).unwrap();
+        let feed = doc.root_element();
+        assert_eq!(
+            feed.tag_name().namespace(),
+            Some("http://www.w3.org/2005/Atom")
+        );
+        let child = |node: ::roxmltree::Node<'_, '_>, name: &str| {
+            node.children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text().map(String::from))
+        };
+        assert_eq!(child(feed, "title").unwrap(), "b&c releases");
+        assert_eq!(child(feed, "updated").unwrap(), "2017-10-24T08:00:00+00:00");
+
+        let entries: Vec<_> = feed
+            .children()
+            .filter(|n| n.has_tag_name("entry"))
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(child(entries[0], "title").unwrap(), "v2 <rc>");
+        assert_eq!(
+            child(entries[0], "id").unwrap(),
+            "https://github.com/a/b&c#v2%20%3Crc%3E"
+        );
+        assert_eq!(
+            child(entries[0], "content").unwrap(),
+            "<h3>Fixes</h3><ul><li>Handle &lt;empty&gt; &quot;responses&quot; &amp; &#39;nulls&#39; \
+             (<a href=\"https://github.com/a/b&amp;c/commit/2222222bbbbbbb\">2222222</a>)</li></ul>"
+        );
+        assert_eq!(
+            child(entries[0], "updated").unwrap(),
+            "2017-10-23T09:05:00+02:00"
+        );
+        assert_eq!(
+            child(entries[1], "updated").unwrap(),
+            "2017-10-24T08:00:00+00:00"
+        );
+        let link = entries[1]
+            .children()
+            .find(|n| n.has_tag_name("link"))
+            .unwrap();
+        assert_eq!(
+            link.attribute("href"),
+            Some("https://github.com/a/b&c/compare/v0...v1")
+        );
+
+        // Nothing to date is dated the same every time
+        assert!(atom(&[]).contains("<updated>1970-01-01T00:00:00+00:00</updated>"));
+    }
+}
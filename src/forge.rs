@@ -0,0 +1,147 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+/// The code hosting services (forges) with known link layouts.
+///
+/// The forge is detected from the remote host name (GitHub is assumed when nothing matches) or picked
+/// explicitly with the [`forge`](struct.OutputPreferences.html#structfield.forge) output preference.
+#[serde(rename_all = "lowercase")]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+pub enum Forge {
+    /// GitHub (and GitHub Enterprise)
+    GitHub,
+
+    /// GitLab (hosted or self-managed)
+    GitLab,
+
+    /// Bitbucket Cloud
+    Bitbucket,
+
+    /// Gitea, Forgejo and Codeberg
+    Gitea,
+}
+
+impl Forge {
+    /// Guess the forge from the remote url
+    pub fn detect(remote_url: &str) -> Self {
+        let host = host(remote_url).to_lowercase();
+        if host.contains("gitlab") {
+            Forge::GitLab
+        } else if host.contains("bitbucket") {
+            Forge::Bitbucket
+        } else if host.contains("gitea") || host.contains("forgejo") || host.contains("codeberg") {
+            Forge::Gitea
+        } else {
+            Forge::GitHub
+        }
+    }
+
+    /// The url prefix that links to a commit when followed by its SHA
+    pub fn commit_url(self, remote_url: &str) -> String {
+        match self {
+            Forge::GitHub | Forge::Gitea => format!("{}/commit/", remote_url),
+            Forge::GitLab => format!("{}/-/commit/", remote_url),
+            Forge::Bitbucket => format!("{}/commits/", remote_url),
+        }
+    }
+
+    /// The url prefix that links to a pull (or merge) request when followed by its number
+    pub fn pr_url(self, remote_url: &str) -> String {
+        match self {
+            Forge::GitHub => format!("{}/pull/", remote_url),
+            Forge::GitLab => format!("{}/-/merge_requests/", remote_url),
+            Forge::Bitbucket => format!("{}/pull-requests/", remote_url),
+            Forge::Gitea => format!("{}/pulls/", remote_url),
+        }
+    }
+
+    /// The url prefix that links to an issue when followed by its number
+    pub fn issue_url(self, remote_url: &str) -> String {
+        match self {
+            Forge::GitHub | Forge::Bitbucket | Forge::Gitea => format!("{}/issues/", remote_url),
+            Forge::GitLab => format!("{}/-/issues/", remote_url),
+        }
+    }
+
+    /// The url that compares the two revisions
+    pub fn compare_url(self, remote_url: &str, from: &str, to: &str) -> String {
+        match self {
+            Forge::GitHub | Forge::Gitea => format!("{}/compare/{}...{}", remote_url, from, to),
+            Forge::GitLab => format!("{}/-/compare/{}...{}", remote_url, from, to),
+            Forge::Bitbucket => format!("{}/branches/compare/{}%0D{}", remote_url, to, from),
+        }
+    }
+}
+
+/// Get the host name of the url
+fn host(url: &str) -> &str {
+    let rest = url.find("://").map_or(url, |i| &url[i + 3..]);
+    let end = rest.find(['/', ':']).unwrap_or(rest.len());
+    &rest[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Forge;
+
+    #[test]
+    fn detect() {
+        assert_eq!(Forge::detect("https://github.com/a/b"), Forge::GitHub);
+        assert_eq!(
+            Forge::detect("https://gitlab.example.com:8443/a/b"),
+            Forge::GitLab
+        );
+        assert_eq!(Forge::detect("https://bitbucket.org/a/b"), Forge::Bitbucket);
+        assert_eq!(Forge::detect("https://codeberg.org/a/b"), Forge::Gitea);
+        assert_eq!(
+            Forge::detect("https://git.example.com/gitlab/b"),
+            Forge::GitHub
+        );
+    }
+
+    #[test]
+    fn links() {
+        let url = "https://gitlab.com/a/b";
+        assert_eq!(
+            Forge::GitLab.commit_url(url),
+            "https://gitlab.com/a/b/-/commit/"
+        );
+        assert_eq!(
+            Forge::GitLab.pr_url(url),
+            "https://gitlab.com/a/b/-/merge_requests/"
+        );
+        assert_eq!(
+            Forge::GitLab.issue_url(url),
+            "https://gitlab.com/a/b/-/issues/"
+        );
+        assert_eq!(
+            Forge::GitLab.compare_url(url, "v1", "v2"),
+            "https://gitlab.com/a/b/-/compare/v1...v2"
+        );
+
+        let url = "https://github.com/a/b";
+        assert_eq!(
+            Forge::GitHub.commit_url(url),
+            "https://github.com/a/b/commit/"
+        );
+        assert_eq!(Forge::GitHub.pr_url(url), "https://github.com/a/b/pull/");
+        assert_eq!(
+            Forge::GitHub.compare_url(url, "v1", "v2"),
+            "https://github.com/a/b/compare/v1...v2"
+        );
+
+        let url = "https://bitbucket.org/a/b";
+        assert_eq!(
+            Forge::Bitbucket.pr_url(url),
+            "https://bitbucket.org/a/b/pull-requests/"
+        );
+        assert_eq!(
+            Forge::Bitbucket.compare_url(url, "v1", "v2"),
+            "https://bitbucket.org/a/b/branches/compare/v2%0Dv1"
+        );
+        assert_eq!(
+            Forge::Gitea.pr_url("https://x.org/a/b"),
+            "https://x.org/a/b/pulls/"
+        );
+    }
+}
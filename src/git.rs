@@ -3,89 +3,347 @@
 
 // All git interactions
 use super::Result;
+use commit::log_contacts;
+use error::git_error;
 use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Output};
 
-/// Check if we're in an git repository?
-pub fn in_git_repository() -> Result<bool> {
-    git(&["rev-parse", "--is-inside-work-tree"]).map(|o| o.status.success())
+/// The git operations the crate needs.
+///
+/// The default implementation ([`GitCommand`](struct.GitCommand.html)) runs the `git` executable
+/// found on `PATH`. Alternative implementations can be used to avoid that dependency.
+pub trait GitBackend {
+    /// The top level directory of the repository (that the paths in it are relative to)
+    fn root(&self) -> &Path;
+
+    /// Check if we're in a git repository?
+    fn in_git_repository(&self) -> Result<bool>;
+
+    /// Get all the tags, in version order (oldest first)
+    fn tags(&self) -> Result<Vec<String>>;
+
+    /// Get the tags reachable from `HEAD`, in version order (oldest first)
+    fn reachable_tags(&self) -> Result<Vec<String>>;
+
+    /// Get the time the tag was made in RFC 2822 format (the tagger time, or the commit time of a
+    /// lightweight tag), none means it is not known
+    fn tag_time(&self, tag: &str) -> Result<Option<String>> {
+        let _ = tag;
+        Ok(None)
+    }
+
+    /// Get the records for all commits returned by `git log <args>` as a single stream
+    fn commits_in_log(&self, args: &[String]) -> Result<String>;
+
+    /// Get the `Name <email>` contacts of the authors and co-authors of every commit returned by
+    /// `git log <args>` (the default reads the whole commit records to find them)
+    fn contacts_in_log(&self, args: &[String]) -> Result<Vec<String>> {
+        self.commits_in_log(args).map(|log| log_contacts(&log))
+    }
+
+    /// Get the files changed by each commit returned by `git log <args>`, in the same order
+    fn changed_files(&self, args: &[String]) -> Result<Vec<ChangedFiles>> {
+        Err(format_err!(
+            "Cannot list the files changed by {} (Reason: unsupported)",
+            args.join(" ")
+        ))
+    }
+
+    /// Get the best common ancestor of the given revisions (none means there is no known one)
+    fn merge_base(&self, revisions: &[String]) -> Result<Option<String>> {
+        let _ = revisions;
+        Ok(None)
+    }
+
+    /// Get the commit record for the given sha
+    fn get_commit_message(&self, sha: &str) -> Result<String>;
+
+    /// Get the fetch url for the given remote
+    fn get_remote_url(&self, name: &str) -> Result<Option<String>>;
+
+    /// Map the given `Name <email>` contacts to their canonical form using `.mailmap`
+    fn check_mailmap(&self, contacts: &[String]) -> Result<Vec<String>>;
 }
 
-/// Get the last tag
-pub fn last_tag() -> Result<Option<String>> {
-    last_tags(1).map(|mut v| v.pop())
+/// The files a commit changes (for a merge, the ones that differ from every parent).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ChangedFiles {
+    /// The commit SHA
+    pub sha: String,
+
+    /// The parent commit SHAs
+    pub parents: Vec<String>,
+
+    /// The changed file paths, relative to the repository root
+    pub files: Vec<String>,
 }
 
-/// Get the SHAs for all commits returned by `git log <args>`
-pub fn commits_in_log(args: &[String]) -> Result<Vec<String>> {
-    let mut log_args = vec!["log", "--format=format:%H"];
-    log_args.extend(args.iter().map(String::as_str));
-    git(&log_args).map(|o| read_lines(&o))
+/// The default backend that runs the `git` executable on `PATH`.
+#[derive(Clone, Debug)]
+pub struct GitCommand {
+    /// The directory git runs in
+    dir: PathBuf,
+
+    /// The top level directory of the repository (the directory git runs in, if it is not known)
+    top: PathBuf,
 }
 
-/// Get the commit message for the given sha
-pub fn get_commit_message(sha: &str) -> Result<Vec<String>> {
-    git(&[
-        "log",
-        "--format=format:%H%n%an%n%aD%n%s%n%b",
-        "--max-count=1",
-        sha,
-    ]).map(|o| read_lines(&o))
+/// The separator that starts each commit record in the log stream
+pub const RECORD_SEPARATOR: char = '\x1e';
+
+/// The separator between the fields of a commit record
+pub const FIELD_SEPARATOR: char = '\x00';
+
+/// The `git log` format that emits one record per commit (sha, parents, author name and email, date
+/// and raw message). The author is mapped with `.mailmap`.
+const LOG_FORMAT: &str = "--format=format:%x1e%H%x00%P%x00%aN%x00%aE%x00%aD%x00%B";
+
+/// The `git log` format that emits one record per commit with its sha and parents (the changed files follow)
+const FILES_FORMAT: &str = "--format=format:%x1e%H%x00%P";
+
+/// The `git log` format that emits one record per commit with its (mailmapped) author and its
+/// co-authors
+const CONTACTS_FORMAT: &str =
+    "--format=format:%x1e%aN <%aE>%x00%(trailers:key=Co-authored-by,valueonly,separator=%x00)";
+
+/// Check if we're in an git repository?
+pub fn in_git_repository() -> Result<bool> {
+    GitCommand::default().in_git_repository()
 }
 
-/// Get the fetch url for the given origin
-pub fn get_remote_url(name: &str) -> Result<Option<String>> {
-    git(&["remote", "get-url", name])
-        .map(|o| read_lines(&o))
-        .map(|mut v: Vec<String>| v.pop().and_then(usable_url))
+impl Default for GitCommand {
+    fn default() -> Self {
+        Self::at(".")
+    }
 }
 
-/// Check if the remote URL is usable for links
-fn usable_url(raw: String) -> Option<String> {
-    let raw_lowercase = raw.to_lowercase();
-    if raw_lowercase.starts_with("http") {
-        if let Some(index) = raw.rfind(".git") {
-            return Some(raw[0..index].to_string());
+impl GitCommand {
+    /// Run git in the given directory instead of the current one
+    pub fn at<P: AsRef<Path>>(dir: P) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        let top = Command::new("git")
+            .current_dir(&dir)
+            .args(["rev-parse", "--show-toplevel"])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .and_then(|o| read_lines::<Vec<String>>(&o).into_iter().next())
+            .map_or_else(|| dir.clone(), PathBuf::from);
+        GitCommand { dir, top }
+    }
+
+    /// Invoke a git command with the given arguments (in the C locale, so that its error messages
+    /// can be classified).
+    fn git(&self, args: &[&str]) -> Result<Output> {
+        trace!("git -C {} {}", self.dir.display(), args.join(" "));
+        let output = Command::new("git")
+            .current_dir(&self.dir)
+            .env("LC_ALL", "C")
+            .args(args)
+            .output()?;
+        if output.status.success() {
+            Ok(output)
         } else {
-            return Some(raw);
+            Err(git_error(&String::from_utf8_lossy(&output.stderr)).into())
         }
-    } else {
-        let ssh_prefix = "git@github.com:";
-        if raw_lowercase.starts_with(ssh_prefix) {
-            let path = &raw[ssh_prefix.len()..];
-            let path = if raw_lowercase.ends_with(".git") {
-                &path[..path.len() - 4]
-            } else {
-                path
-            };
-            let mut github_url = String::from("https://github.com/");
-            github_url.push_str(path);
-            return Some(github_url);
+    }
+}
+
+impl GitBackend for GitCommand {
+    fn root(&self) -> &Path {
+        &self.top
+    }
+
+    fn in_git_repository(&self) -> Result<bool> {
+        self.git(&["rev-parse", "--is-inside-work-tree"])
+            .map(|o| o.status.success())
+    }
+
+    fn tags(&self) -> Result<Vec<String>> {
+        self.git(&[
+            "for-each-ref",
+            "--sort=version:refname",
+            "--format=%(refname:short)",
+            "refs/tags/*",
+        ])
+        .map(|o| read_lines(&o))
+    }
+
+    fn reachable_tags(&self) -> Result<Vec<String>> {
+        self.git(&[
+            "for-each-ref",
+            "--merged=HEAD",
+            "--sort=version:refname",
+            "--format=%(refname:short)",
+            "refs/tags/*",
+        ])
+        .map(|o| read_lines(&o))
+    }
+
+    fn tag_time(&self, tag: &str) -> Result<Option<String>> {
+        let tag = format!("refs/tags/{}", tag);
+        self.git(&[
+            "for-each-ref",
+            "--count=1",
+            "--format=%(creatordate:rfc2822)",
+            &tag,
+        ])
+        .map(|o| {
+            read_lines::<Vec<String>>(&o)
+                .pop()
+                .filter(|t| !t.is_empty())
+        })
+    }
+
+    fn commits_in_log(&self, args: &[String]) -> Result<String> {
+        let mut log_args = vec!["log", LOG_FORMAT];
+        log_args.extend(args.iter().map(String::as_str));
+        self.git(&log_args).map(|o| read_text(&o))
+    }
+
+    fn contacts_in_log(&self, args: &[String]) -> Result<Vec<String>> {
+        let mut log_args = vec!["log", CONTACTS_FORMAT];
+        log_args.extend(args.iter().map(String::as_str));
+        self.git(&log_args).map(|o| read_contacts(&read_text(&o)))
+    }
+
+    fn changed_files(&self, args: &[String]) -> Result<Vec<ChangedFiles>> {
+        let mut log_args = vec!["log", FILES_FORMAT, "--name-only", "--cc", "-z"];
+        log_args.extend(args.iter().map(String::as_str));
+        self.git(&log_args)
+            .map(|o| read_changed_files(&read_text(&o)))
+    }
+
+    fn merge_base(&self, revisions: &[String]) -> Result<Option<String>> {
+        let mut args = vec!["merge-base", "--octopus"];
+        args.extend(revisions.iter().map(String::as_str));
+        self.git(&args).map(|o| read_lines::<Vec<String>>(&o).pop())
+    }
+
+    fn get_commit_message(&self, sha: &str) -> Result<String> {
+        self.git(&["log", LOG_FORMAT, "--max-count=1", sha])
+            .map(|o| read_text(&o))
+    }
+
+    fn get_remote_url(&self, name: &str) -> Result<Option<String>> {
+        self.git(&["remote", "get-url", name])
+            .map(|o| read_lines(&o))
+            .map(|mut v: Vec<String>| v.pop().and_then(usable_url))
+    }
+
+    fn check_mailmap(&self, contacts: &[String]) -> Result<Vec<String>> {
+        if contacts.is_empty() {
+            return Ok(Vec::new());
         }
+        let mut args = vec!["check-mailmap"];
+        args.extend(contacts.iter().map(String::as_str));
+        self.git(&args).map(|o| read_lines(&o))
     }
-    None
 }
 
-/// Get the last n tags
-fn last_tags(n: i32) -> Result<Vec<String>> {
-    git(&[
-        "for-each-ref",
-        &format!("--count={}", n),
-        "--sort=-taggerdate",
-        "--format=%(refname:short)",
-        "refs/tags/*",
-    ]).map(|o| read_lines(&o))
+/// Format a commit record the same way `git log` does with our format
+#[cfg(any(test, feature = "libgit2"))]
+pub fn format_record(
+    sha: &str,
+    parents: &str,
+    author: &str,
+    email: &str,
+    time: &str,
+    message: &str,
+) -> String {
+    let fields = [sha, parents, author, email, time, message];
+    let mut record = String::new();
+    record.push(RECORD_SEPARATOR);
+    record.push_str(&fields.join(&FIELD_SEPARATOR.to_string()));
+    record
 }
 
-/// Invoke a git command with the given arguments.
-fn git(args: &[&str]) -> Result<Output> {
-    trace!("git {}", args.join(" "));
-    let output = Command::new("git").args(args).output()?;
-    if output.status.success() {
-        Ok(output)
+/// Turn the remote URL into a web (`https://`) URL usable for links.
+///
+/// Understands `http(s)://`, `ssh://` and `git://` URLs as well as the scp-like `user@host:path` syntax.
+/// Local paths are not usable.
+pub fn usable_url(raw: String) -> Option<String> {
+    let raw = raw.trim().trim_end_matches('/');
+    let raw = if raw.to_lowercase().ends_with(".git") {
+        &raw[..raw.len() - 4]
+    } else {
+        raw
+    };
+
+    // Split the URL into the scheme and the rest
+    let (scheme, rest) = match raw.find("://") {
+        Some(index) => (raw[..index].to_lowercase(), &raw[index + 3..]),
+        // Windows drive letters (`C:\...`) look like scp-like URLs with one letter hosts
+        None if matches!(raw.find(':'), Some(i) if i > 1) && !raw.starts_with('/') => {
+            (String::from("scp"), raw)
+        }
+        None => return None,
+    };
+
+    // Drop any user name
+    let rest = match rest.find('@') {
+        Some(index) if index < rest.find('/').unwrap_or(rest.len()) => &rest[index + 1..],
+        _ => rest,
+    };
+
+    // Split the host (and port) from the path
+    let (host, path) = match scheme.as_str() {
+        "http" | "https" => return Some(format!("{}://{}", scheme, rest)),
+        "scp" => rest.split_at(rest.find(':')?),
+        "ssh" | "git" | "git+ssh" | "ssh+git" => rest.split_at(rest.find('/')?),
+        _ => return None,
+    };
+
+    // The SSH port is not the web port, so drop it
+    let host = host.split(':').next().unwrap_or(host);
+    let path = path.trim_start_matches([':', '/']);
+    if host.is_empty() || path.is_empty() {
+        None
     } else {
-        Err(format_err!("{}", String::from_utf8_lossy(&output.stderr)))
+        Some(format!("https://{}/{}", host, path))
+    }
+}
+
+/// Read the changed files records (the parents end at a newline, and each file at a NUL)
+fn read_changed_files(log: &str) -> Vec<ChangedFiles> {
+    let mut commits = Vec::new();
+    for record in log.split(RECORD_SEPARATOR).skip(1) {
+        let mut fields = record.splitn(2, FIELD_SEPARATOR);
+        let sha = fields.next().unwrap_or_default();
+        let rest = fields.next().unwrap_or_default();
+        let mut lines = rest.splitn(2, '\n');
+        let parents = lines.next().unwrap_or_default();
+        let files = lines.next().unwrap_or_default();
+        commits.push(ChangedFiles {
+            sha: sha.to_owned(),
+            parents: parents
+                .trim_end_matches(FIELD_SEPARATOR)
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+            files: files
+                .split(FIELD_SEPARATOR)
+                .filter(|f| !f.is_empty())
+                .map(String::from)
+                .collect(),
+        });
     }
+    commits
+}
+
+/// Read the contacts records (each contact ends at a NUL)
+fn read_contacts(log: &str) -> Vec<String> {
+    log.split([RECORD_SEPARATOR, FIELD_SEPARATOR])
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Read the output as a single String.
+fn read_text(o: &Output) -> String {
+    String::from_utf8_lossy(&o.stdout).into_owned()
 }
 
 /// Read the lines from the output and gather them into a String collection.
@@ -97,40 +355,191 @@ fn read_lines<T: FromIterator<String>>(o: &Output) -> T {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use super::{GitBackend, GitCommand};
+    use std::env::temp_dir;
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    /// Make a scratch repository with the given name. It has `v0.1.0`, `v0.2.0` and an untagged
+    /// commit on `HEAD`, and a `v9.0.0` tag on a side branch.
+    pub fn scratch_repository(name: &str) -> PathBuf {
+        use std::fs;
+        let dir = temp_dir().join(format!("changelog-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .current_dir(&dir)
+                .args(["-c", "user.name=Jane", "-c", "user.email=jane@x.org"])
+                .args(["-c", "commit.gpgsign=false", "-c", "tag.gpgsign=false"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {}", args.join(" "));
+        };
+        let commit = |message: &str| git(&["commit", "-q", "--allow-empty", "-m", message]);
+
+        git(&["init", "-q"]);
+        commit("First\n\n- feature: One");
+        git(&["tag", "v0.1.0"]);
+        commit("Second\n\n- fix: Two");
+        git(&["tag", "v0.2.0"]);
+        commit("Third\n\n- fix: Three");
+        git(&["checkout", "-q", "-b", "side", "v0.1.0"]);
+        commit("Side\n\n- fix: Side");
+        git(&["tag", "v9.0.0"]);
+        git(&["checkout", "-q", "-"]);
+        dir
+    }
+
     #[test]
     fn in_git_repository() {
         assert!(super::in_git_repository().unwrap());
+        assert!(GitCommand::at("src").in_git_repository().unwrap());
+        assert!(GitCommand::at("no-such-dir").in_git_repository().is_err());
+    }
+
+    #[test]
+    fn root() {
+        // The paths in the repository are relative to its top level, wherever git runs in it
+        let top = GitCommand::default().root().to_path_buf();
+        assert_eq!(GitCommand::at("src").root(), top);
+        assert_eq!(
+            GitCommand::at("no-such-dir").root(),
+            PathBuf::from("no-such-dir")
+        );
     }
 
     #[test]
     fn last_tag() {
-        assert!(super::last_tag().is_ok());
+        use input::Conventions;
+        use version::ReleaseTags;
+        let git = GitCommand::default();
+        let tags = ReleaseTags::new(&Conventions::default()).unwrap();
+        let last = tags.sort(git.reachable_tags().unwrap()).pop().map(|t| t.0);
+        assert_eq!(tags.last(&git), last);
+    }
+
+    #[test]
+    fn tag_time() {
+        use chrono::DateTime;
+        let git = GitCommand::default();
+        for tag in git.tags().unwrap() {
+            let time = git.tag_time(&tag).unwrap().unwrap();
+            assert!(DateTime::parse_from_rfc2822(&time).is_ok());
+        }
+        assert_eq!(git.tag_time("no-such-tag").unwrap(), None);
+    }
+
+    #[test]
+    fn tags() {
+        let dir = scratch_repository("tags");
+        let git = GitCommand::at(&dir);
+        assert_eq!(git.tags().unwrap(), ["v0.1.0", "v0.2.0", "v9.0.0"]);
+        assert_eq!(git.reachable_tags().unwrap(), ["v0.1.0", "v0.2.0"]);
+        ::std::fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
     fn commits_in_log() {
-        use super::commits_in_log;
+        use super::{FIELD_SEPARATOR, RECORD_SEPARATOR};
+        let git = GitCommand::default();
+
+        // Pick the SHAs from the records in the log
+        let shas = |log: &str| -> Vec<String> {
+            log.split(RECORD_SEPARATOR)
+                .skip(1)
+                .filter_map(|r| r.split(FIELD_SEPARATOR).next())
+                .map(String::from)
+                .collect()
+        };
+
         let mut range = vec![String::from("v0.1.1..v0.2.0")];
-        let commits = commits_in_log(&range);
+        let commits = git.commits_in_log(&range);
         assert!(commits.is_ok(), "{:?}", commits);
-        let forward = commits.unwrap();
+        let forward = shas(&commits.unwrap());
         assert_eq!(forward.len(), 2);
 
         // Add a `git log` option
         range.push(String::from("--reverse"));
-        let commits = commits_in_log(&range);
+        let commits = git.commits_in_log(&range);
         assert!(commits.is_ok(), "{:?}", commits);
-        let mut backward = commits.unwrap();
+        let mut backward = shas(&commits.unwrap());
         backward.reverse();
         assert_eq!(backward, forward);
     }
 
+    #[test]
+    fn changed_files() {
+        use super::{read_changed_files, ChangedFiles};
+        let log =
+            "\x1ea\x00b c\nsrc/a.rs\x00my file.md\x00\x00\x1eb\x00c\x00\x1ec\x00\nREADME.md\x00";
+        let files = |sha: &str, parents: &[&str], files: &[&str]| ChangedFiles {
+            sha: sha.to_owned(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            files: files.iter().map(|f| f.to_string()).collect(),
+        };
+        assert_eq!(
+            read_changed_files(log),
+            vec![
+                files("a", &["b", "c"], &["src/a.rs", "my file.md"]),
+                files("b", &["c"], &[]),
+                files("c", &[], &["README.md"]),
+            ]
+        );
+
+        let git = GitCommand::default();
+        let head = git.changed_files(&[String::from("--max-count=1")]).unwrap();
+        assert_eq!(head.len(), 1);
+        let revisions = vec![String::from("HEAD"), String::from("HEAD~1")];
+        assert_eq!(
+            git.merge_base(&revisions).unwrap(),
+            Some(head[0].parents[0].clone())
+        );
+    }
+
+    #[test]
+    fn contacts_in_log() {
+        use super::{format_record, read_contacts};
+        use commit::log_contacts;
+        let log = "\x1eJane <j@x>\x00\n\x1eJoe <o@x>\x00Jane <j@x>\x00Ann <a@x>";
+        assert_eq!(
+            read_contacts(log),
+            ["Jane <j@x>", "Joe <o@x>", "Jane <j@x>", "Ann <a@x>"]
+        );
+
+        // The default reads the whole records
+        let message = "Subject\n\nBody\n\nCo-authored-by: Ann <a@x.org>";
+        let time = "Sun, 22 Oct 2017 17:26:56 -0400";
+        let record = format_record("abc", "", "Joe", "joe@x.org", time, message);
+        assert_eq!(log_contacts(&record), ["Joe <joe@x.org>", "Ann <a@x.org>"]);
+
+        let git = GitCommand::default();
+        let range = vec![String::from("HEAD~3..HEAD")];
+        let contacts = log_contacts(&git.commits_in_log(&range).unwrap());
+        assert_eq!(git.contacts_in_log(&range).unwrap(), contacts);
+    }
+
     #[test]
     fn get_commit_message() {
-        use super::get_commit_message;
-        assert!(get_commit_message("v0.1.1").is_ok());
-        assert!(get_commit_message("bad").is_err());
+        use super::{FIELD_SEPARATOR, RECORD_SEPARATOR};
+        let git = GitCommand::default();
+        let message = git.get_commit_message("v0.1.1");
+        assert!(message.is_ok());
+        let message = message.unwrap();
+        assert!(message.starts_with(RECORD_SEPARATOR));
+        assert_eq!(message.matches(FIELD_SEPARATOR).count(), 5);
+        assert!(git.get_commit_message("bad").is_err());
+    }
+
+    #[test]
+    fn check_mailmap() {
+        let git = GitCommand::default();
+        let contacts = vec![String::from("Jane Doe <jane@example.com>")];
+        assert_eq!(git.check_mailmap(&contacts).unwrap(), contacts);
+        assert!(git.check_mailmap(&[]).unwrap().is_empty());
+        assert!(git.check_mailmap(&[String::from("no email")]).is_err());
     }
 
     #[test]
@@ -142,13 +551,55 @@ mod tests {
         assert_eq!(usable_url(usable.to_string()), Some(usable.to_string()));
         assert_eq!(usable_url(raw), Some(usable.to_string()));
         assert_eq!(usable_url(ssh), Some(usable.to_string()));
+
+        // Other forges, self-hosted servers and URL styles
+        let check = |raw: &str, usable: &str| {
+            assert_eq!(
+                usable_url(raw.to_string()),
+                Some(usable.to_string()),
+                "{}",
+                raw
+            );
+        };
+        check(
+            "git@gitlab.com:group/sub/project.git",
+            "https://gitlab.com/group/sub/project",
+        );
+        check(
+            "ssh://git@git.example.com:2222/team/repo.git",
+            "https://git.example.com/team/repo",
+        );
+        check(
+            "ssh://git.example.com/team/repo",
+            "https://git.example.com/team/repo",
+        );
+        check(
+            "https://user@bitbucket.org/team/repo.git",
+            "https://bitbucket.org/team/repo",
+        );
+        check(
+            "https://git.example.com:8443/team/repo/",
+            "https://git.example.com:8443/team/repo",
+        );
+        check(
+            "http://git.example.com/team/repo.github.io",
+            "http://git.example.com/team/repo.github.io",
+        );
+        check(
+            "git://git.example.com/team/repo.git",
+            "https://git.example.com/team/repo",
+        );
+        assert_eq!(usable_url(String::from("/srv/git/repo.git")), None);
+        assert_eq!(usable_url(String::from("file:///srv/git/repo.git")), None);
+        assert_eq!(usable_url(String::from("../repo")), None);
+        assert_eq!(usable_url(String::from("C:\\repo")), None);
     }
 
     #[test]
     fn get_remote_url() {
-        use super::get_remote_url;
-        let found = get_remote_url("origin").unwrap();
-        assert!(get_remote_url("bad").is_err());
+        let git = GitCommand::default();
+        let found = git.get_remote_url("origin").unwrap();
+        assert!(git.get_remote_url("bad").is_err());
         assert!(found.is_some());
         let found = found.unwrap();
         assert!(found.starts_with("https://github.com/"));
@@ -0,0 +1,438 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+// A git backend that uses libgit2 instead of the `git` executable
+use super::Result;
+use chrono::{FixedOffset, TimeZone};
+use error::Error;
+use git::{format_record, usable_url, ChangedFiles, GitBackend};
+use git2::{
+    Commit, DiffOptions, ErrorCode, Mailmap, Oid, Repository, Revwalk, Signature, Sort, Time,
+};
+use std::path::Path;
+
+/// A backend that reads the repository with [libgit2](https://libgit2.org).
+///
+/// This backend does not need a `git` executable, but it only understands a subset of the `git log`
+/// arguments: revisions (`HEAD`, `v0.1.0`), exclusions (`^v0.1.0`), ranges (`v0.1.0..HEAD`), the
+/// `--reverse`, `--merges` and `--no-merges` flags and the paths (or globs) after a `--`.
+pub struct LibGit2 {
+    /// The repository
+    repo: Repository,
+}
+
+/// The `git log` flags the walk understands
+#[derive(Default)]
+struct LogFlags {
+    /// List the commits in reverse order
+    reverse: bool,
+
+    /// Only list merge commits (`Some(true)`) or only the others (`Some(false)`)
+    merges: Option<bool>,
+
+    /// Only list the commits that change these paths (all of them, when empty)
+    paths: Vec<String>,
+}
+
+impl LibGit2 {
+    /// Open the repository that contains the current directory
+    pub fn new() -> Result<Self> {
+        Self::open(".")
+    }
+
+    /// Open the repository that contains the given path
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Repository::discover(path)
+            .map(|repo| LibGit2 { repo })
+            .map_err(|e| {
+                Error::NotARepository(format!("Cannot open repository (Reason: {})", e)).into()
+            })
+    }
+
+    /// Find the commit the revision names
+    fn revision(&self, revision: &str) -> Result<Oid> {
+        let object = self
+            .repo
+            .revparse_single(revision)
+            .map_err(|e| bad_revision(revision, e))?;
+        Ok(object.peel_to_commit()?.id())
+    }
+
+    /// Set up a walk over the commits selected by the given log arguments
+    fn walk(&self, args: &[String]) -> Result<(Revwalk<'_>, LogFlags)> {
+        let mut walk = self.repo.revwalk()?;
+        let mut flags = LogFlags::default();
+        let mut pushed = false;
+        let mut paths = false;
+
+        for arg in args {
+            if paths {
+                flags.paths.push(pathspec(arg));
+            } else if arg == "--" {
+                paths = true;
+            } else if arg == "--reverse" {
+                flags.reverse = true;
+            } else if arg == "--merges" {
+                flags.merges = Some(true);
+            } else if arg == "--no-merges" {
+                flags.merges = Some(false);
+            } else if arg.starts_with('-') {
+                return Err(format_err!("Unsupported log argument '{}'", arg));
+            } else if arg.contains("..") {
+                walk.push_range(arg).map_err(|e| bad_revision(arg, e))?;
+                pushed = true;
+            } else if let Some(hidden) = arg.strip_prefix('^') {
+                walk.hide(self.revision(hidden)?)?;
+            } else {
+                walk.push(self.revision(arg)?)?;
+                pushed = true;
+            }
+        }
+
+        // Like `git log`, default to everything reachable from `HEAD`
+        if !pushed {
+            walk.push_head()?;
+        }
+
+        // Newest first, but (like `git log`) never a parent before its children, even when commits
+        // share a timestamp
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        Ok((walk, flags))
+    }
+
+    /// Get the commits selected by the given log arguments
+    fn log(&self, args: &[String]) -> Result<Vec<Commit<'_>>> {
+        let (walk, flags) = self.walk(args)?;
+        let mut commits = Vec::new();
+        for id in walk {
+            let commit = self.repo.find_commit(id?)?;
+            if !matches!(flags.merges, Some(m) if m != (commit.parent_count() > 1))
+                && self.touches(&commit, &flags.paths)?
+            {
+                commits.push(commit);
+            }
+        }
+        if flags.reverse {
+            commits.reverse();
+        }
+        Ok(commits)
+    }
+
+    /// Get the files (among the given paths, if any) the commit changes from each of its parents
+    fn diffs(&self, commit: &Commit, paths: &[String]) -> Result<Vec<Vec<String>>> {
+        let mut options = DiffOptions::new();
+        for path in paths {
+            options.pathspec(path);
+        }
+        let tree = commit.tree()?;
+        let mut parents = Vec::new();
+        for parent in commit.parents() {
+            parents.push(Some(parent.tree()?));
+        }
+        if parents.is_empty() {
+            parents.push(None);
+        }
+
+        let mut diffs = Vec::new();
+        for parent in parents {
+            let diff =
+                self.repo
+                    .diff_tree_to_tree(parent.as_ref(), Some(&tree), Some(&mut options))?;
+            let files = diff
+                .deltas()
+                .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+                .map(|p| p.to_string_lossy().into_owned())
+                .collect();
+            diffs.push(files);
+        }
+        Ok(diffs)
+    }
+
+    /// Check if the commit changes any of the given paths (every commit does, if there are none)
+    fn touches(&self, commit: &Commit, paths: &[String]) -> Result<bool> {
+        if paths.is_empty() {
+            return Ok(true);
+        }
+
+        // Like `git log`, a merge that matches one of its parents changes nothing
+        let diffs = self.diffs(commit, paths)?;
+        Ok(diffs.iter().all(|files| !files.is_empty()))
+    }
+}
+
+impl GitBackend for LibGit2 {
+    fn root(&self) -> &Path {
+        self.repo.workdir().unwrap_or_else(|| self.repo.path())
+    }
+
+    fn in_git_repository(&self) -> Result<bool> {
+        // Like `git rev-parse --is-inside-work-tree`, which succeeds in any repository (even a bare
+        // one or its git directory), the repository opened
+        Ok(true)
+    }
+
+    fn tags(&self) -> Result<Vec<String>> {
+        let mut tags: Vec<String> = self
+            .repo
+            .tag_names(None)?
+            .iter()
+            .filter_map(|n| n.map(String::from))
+            .collect();
+
+        // Like `--sort=version:refname`, compare the numeric parts as numbers
+        tags.sort_by_key(|t| version_key(t));
+        Ok(tags)
+    }
+
+    fn reachable_tags(&self) -> Result<Vec<String>> {
+        // Like `--merged=HEAD`, keep the tags of `HEAD` and its ancestors
+        let head = self.repo.head()?.peel_to_commit()?.id();
+        let mut tags = Vec::new();
+        for tag in self.tags()? {
+            let object = self.repo.revparse_single(&format!("refs/tags/{}", tag))?;
+            if let Ok(commit) = object.peel_to_commit() {
+                if commit.id() == head || self.repo.graph_descendant_of(head, commit.id())? {
+                    tags.push(tag);
+                }
+            }
+        }
+        Ok(tags)
+    }
+
+    fn tag_time(&self, tag: &str) -> Result<Option<String>> {
+        let object = self
+            .repo
+            .revparse_single(&format!("refs/tags/{}", tag))
+            .map_err(|e| bad_revision(tag, e))?;
+
+        // Like `%(creatordate)`, use the tagger time of annotated tags and the commit time of others
+        let when = match object.as_tag().and_then(|t| t.tagger()) {
+            Some(tagger) => tagger.when(),
+            None => object.peel_to_commit()?.committer().when(),
+        };
+        Ok(rfc2822(when))
+    }
+
+    fn commits_in_log(&self, args: &[String]) -> Result<String> {
+        let mailmap = self.repo.mailmap()?;
+        let mut records = Vec::new();
+        for commit in self.log(args)? {
+            records.push(record(&commit, &mailmap)?);
+        }
+        Ok(records.join("\n"))
+    }
+
+    fn changed_files(&self, args: &[String]) -> Result<Vec<ChangedFiles>> {
+        let mut changes = Vec::new();
+        for commit in self.log(args)? {
+            // Like `git log --cc`, the files of a merge are the ones that differ from every parent
+            let mut diffs = self.diffs(&commit, &[])?.into_iter();
+            let mut files = diffs.next().unwrap_or_default();
+            for other in diffs {
+                files.retain(|f| other.contains(f));
+            }
+            changes.push(ChangedFiles {
+                sha: commit.id().to_string(),
+                parents: commit.parent_ids().map(|p| p.to_string()).collect(),
+                files,
+            });
+        }
+        Ok(changes)
+    }
+
+    fn merge_base(&self, revisions: &[String]) -> Result<Option<String>> {
+        let mut ids = Vec::new();
+        for revision in revisions {
+            ids.push(self.revision(revision)?);
+        }
+        match self.repo.merge_base_many(&ids) {
+            Ok(id) => Ok(Some(id.to_string())),
+            Err(ref e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_commit_message(&self, sha: &str) -> Result<String> {
+        let commit = self.repo.revparse_single(sha)?.peel_to_commit()?;
+        record(&commit, &self.repo.mailmap()?)
+    }
+
+    fn get_remote_url(&self, name: &str) -> Result<Option<String>> {
+        let remote = self.repo.find_remote(name)?;
+        Ok(remote.url().map(String::from).and_then(usable_url))
+    }
+
+    fn check_mailmap(&self, contacts: &[String]) -> Result<Vec<String>> {
+        let mailmap = self.repo.mailmap()?;
+        let mut mapped = Vec::new();
+        for contact in contacts {
+            let open = contact.rfind('<');
+            let close = contact.rfind('>');
+            let (name, email) = match (open, close) {
+                (Some(open), Some(close)) if open < close => {
+                    (contact[..open].trim(), &contact[open + 1..close])
+                }
+                _ => return Err(format_err!("Invalid contact '{}'", contact)),
+            };
+            let signature = Signature::new(name, email, &Time::new(0, 0))?;
+            let resolved = mailmap.resolve_signature(&signature)?;
+            mapped.push(format!(
+                "{} <{}>",
+                resolved.name().unwrap_or_default(),
+                resolved.email().unwrap_or_default()
+            ));
+        }
+        Ok(mapped)
+    }
+}
+
+/// Split the tag into alternating text and number parts so they sort in version order
+fn version_key(tag: &str) -> Vec<(u64, String)> {
+    let mut key = Vec::new();
+    let mut rest = tag;
+    while let Some(first) = rest.chars().next() {
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (part, tail) = rest.split_at(end);
+        if digits {
+            key.push((part.parse().unwrap_or(0), String::new()));
+        } else {
+            key.push((0, part.to_string()));
+        }
+        rest = tail;
+    }
+    key
+}
+
+/// Report the revisions that cannot be resolved as bad revisions
+fn bad_revision(revision: &str, why: git2::Error) -> ::anyhow::Error {
+    match why.code() {
+        ErrorCode::NotFound | ErrorCode::InvalidSpec | ErrorCode::Ambiguous => {
+            Error::BadRevision(format!("{} ({})", revision, why.message())).into()
+        }
+        _ => why.into(),
+    }
+}
+
+/// Drop the magic (e.g. `:(top,glob)`) of a `git log` pathspec (libgit2 paths are globs from the top)
+fn pathspec(arg: &str) -> String {
+    match (arg.starts_with(":("), arg.find(')')) {
+        (true, Some(end)) => arg[end + 1..].to_owned(),
+        _ => arg.to_owned(),
+    }
+}
+
+/// Format the git time in RFC 2822 format (none if it is out of range)
+fn rfc2822(when: Time) -> Option<String> {
+    FixedOffset::east_opt(when.offset_minutes() * 60)
+        .and_then(|offset| offset.timestamp_opt(when.seconds(), 0).single())
+        .map(|time| time.to_rfc2822())
+}
+
+/// Format the commit as a log record (with the author mapped by the mailmap)
+fn record(commit: &Commit, mailmap: &Mailmap) -> Result<String> {
+    let author = commit.author_with_mailmap(mailmap)?;
+    let when = author.when();
+    let time = rfc2822(when).unwrap_or_default();
+
+    let parents: Vec<String> = commit.parent_ids().map(|p| p.to_string()).collect();
+    Ok(format_record(
+        &commit.id().to_string(),
+        &parents.join(" "),
+        author.name().unwrap_or_default(),
+        author.email().unwrap_or_default(),
+        &time,
+        commit.message().unwrap_or_default(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{version_key, LibGit2};
+    use git::{GitBackend, GitCommand, FIELD_SEPARATOR, RECORD_SEPARATOR};
+
+    #[test]
+    fn libgit2_backend() {
+        let git = LibGit2::new().unwrap();
+        assert!(git.in_git_repository().unwrap());
+        assert!(git.tags().is_ok());
+        assert_eq!(
+            git.reachable_tags().unwrap(),
+            GitCommand::default().reachable_tags().unwrap()
+        );
+
+        let head = git.get_commit_message("HEAD").unwrap();
+        assert!(head.starts_with(RECORD_SEPARATOR));
+        assert_eq!(head.matches(FIELD_SEPARATOR).count(), 5);
+
+        let log = git.commits_in_log(&[String::from("HEAD")]).unwrap();
+        assert!(log.starts_with(&head));
+        assert!(git.commits_in_log(&[String::from("--author")]).is_err());
+
+        // Merges and the other commits make up the whole log
+        let count = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            git.commits_in_log(&args)
+                .unwrap()
+                .matches(RECORD_SEPARATOR)
+                .count()
+        };
+        let merges = count(&["HEAD", "--merges"]);
+        assert_eq!(merges + count(&["HEAD", "--no-merges"]), count(&["HEAD"]));
+
+        // Paths pick the same commits as they do with git
+        let paths = [
+            "HEAD",
+            "--",
+            ":(top,glob)src/assets",
+            ":(top,glob)src/*/*.hbs",
+        ];
+        let args: Vec<String> = paths.iter().map(|a| a.to_string()).collect();
+        let git_log = GitCommand::default().commits_in_log(&args).unwrap();
+        assert_eq!(count(&paths), git_log.matches(RECORD_SEPARATOR).count());
+        assert!(count(&paths) < count(&["HEAD"]));
+        let recent = [String::from("HEAD~5..HEAD")];
+        assert_eq!(
+            git.changed_files(&recent).unwrap(),
+            GitCommand::default().changed_files(&recent).unwrap()
+        );
+        assert!(git.get_commit_message("bad").is_err());
+        for tag in git.tags().unwrap() {
+            assert_eq!(
+                git.tag_time(&tag).unwrap(),
+                GitCommand::default().tag_time(&tag).unwrap()
+            );
+        }
+        assert!(git.tag_time("no-such-tag").is_err());
+
+        let contacts = vec![String::from("Jane Doe <jane@example.com>")];
+        assert_eq!(git.check_mailmap(&contacts).unwrap(), contacts);
+        assert!(git.check_mailmap(&[String::from("no email")]).is_err());
+    }
+
+    #[test]
+    fn bare_repository() {
+        use git2::Repository;
+        use std::env::temp_dir;
+        use std::fs;
+
+        // Like git, a bare repository is a repository
+        let dir = temp_dir().join(format!("changelog-bare-{}", ::std::process::id()));
+        Repository::init_bare(&dir).unwrap();
+        assert!(LibGit2::open(&dir).unwrap().in_git_repository().unwrap());
+        assert!(GitCommand::at(&dir).in_git_repository().unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn version_order() {
+        let mut tags = vec!["v0.10.0", "v0.2.1", "v0.2.0", "v1.0.0", "v0.9.12"];
+        tags.sort_by_key(|t| version_key(t));
+        assert_eq!(
+            tags,
+            vec!["v0.2.0", "v0.2.1", "v0.9.12", "v0.10.0", "v1.0.0"]
+        );
+    }
+}
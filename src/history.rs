@@ -0,0 +1,163 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+use changelog::{try_from_log_known, ChangeLog};
+use error::Error;
+use git::{GitBackend, GitCommand};
+use input::Configuration;
+use std::collections::HashSet;
+use version::ReleaseTags;
+
+/// The title of the release that collects changes after the last tag.
+pub const UNRELEASED: &str = "Unreleased";
+
+/// A changelog for every release in the repository history.
+#[derive(Debug, Default, Serialize, Eq, PartialEq)]
+pub struct History {
+    /// The releases, newest first.
+    pub releases: Vec<Release>,
+}
+
+/// The changes that went into a single release.
+#[derive(Debug, Default, Serialize, Eq, PartialEq)]
+pub struct Release {
+    /// The release tag (none for the unreleased changes).
+    pub tag: Option<String>,
+
+    /// The release title (the tag, or "Unreleased").
+    pub title: String,
+
+    /// The changes in the release.
+    #[serde(flatten)]
+    pub changelog: ChangeLog,
+}
+
+impl History {
+    /// Generate the history for the current repository
+    pub fn new() -> Self {
+        Self::from_tags(&Configuration::new()).unwrap_or_else(|why| {
+            error!("Cannot generate the history (Reason: {})", why);
+            Self::default()
+        })
+    }
+
+    /// Generate the history using the given configuration, failing if the configuration is invalid
+    pub fn from_tags(config: &Configuration) -> Result<Self, Error> {
+        Self::from_tags_with(config, &GitCommand::default())
+    }
+
+    /// Generate the history using the given configuration, reading the log with the given backend
+    pub fn from_tags_with(config: &Configuration, backend: &dyn GitBackend) -> Result<Self, Error> {
+        // Get the release tags in version order
+        let release_tags = ReleaseTags::new(&config.conventions)?;
+        let tags = release_tags.all(backend);
+
+        // Each tag covers the commits since the one before it (and whoever contributed to the
+        // releases before it is not new)
+        let mut releases = Vec::new();
+        let mut known = HashSet::new();
+        let mut previous: Option<&str> = None;
+        for tag in &tags {
+            let range = match previous {
+                Some(from) => format!("{}..{}", from, tag),
+                None => tag.to_owned(),
+            };
+            let mut release = Release::new(Some(tag), range, &known, config, backend);
+            release.changelog.version = release_tags.version(tag).map(|v| v.to_string());
+            known.extend(release.changelog.contributors.iter().map(|c| c.identity()));
+            releases.push(release);
+            previous = Some(tag);
+        }
+
+        // Whatever came after the last tag is yet to be released
+        let range = previous.map_or_else(|| String::from("HEAD"), |t| format!("{}..HEAD", t));
+        let unreleased = Release::new(None, range, &known, config, backend);
+        if !unreleased.changelog.commits.is_empty() {
+            releases.push(unreleased);
+        }
+
+        // Newest first
+        releases.reverse();
+        Ok(History { releases })
+    }
+}
+
+impl Release {
+    /// Generate the release for the given tag and range, with the contributors to earlier releases
+    fn new(
+        tag: Option<&str>,
+        range: String,
+        known: &HashSet<String>,
+        config: &Configuration,
+        backend: &dyn GitBackend,
+    ) -> Self {
+        let title = tag.unwrap_or(UNRELEASED).to_owned();
+        let mut changelog = try_from_log_known(vec![range.clone()], known, config, backend)
+            .unwrap_or_else(|why| {
+                error!("Invalid log input {} (Reason: {})", range, why);
+                let mut log = ChangeLog::from_with(Vec::new().into_iter(), config, backend);
+                log.range = range;
+                log
+            });
+        if let Some(tag) = tag {
+            changelog.tag_time = backend.tag_time(tag).unwrap_or_else(|why| {
+                warn!("Cannot find when {} was tagged (Reason: {})", tag, why);
+                None
+            });
+        }
+        Release {
+            tag: tag.map(str::to_owned),
+            title,
+            changelog,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn history() {
+        use super::{History, UNRELEASED};
+        use git::{GitBackend, GitCommand};
+
+        let history = History::new();
+        let tags = GitCommand::default().tags().unwrap();
+        assert!(history.releases.len() >= tags.len());
+        for release in &history.releases {
+            match release.tag {
+                Some(ref tag) => assert_eq!(&release.title, tag),
+                None => assert_eq!(release.title, UNRELEASED),
+            }
+        }
+    }
+
+    #[test]
+    fn releases() {
+        use super::History;
+        use git::tests::scratch_repository;
+        use git::GitCommand;
+        use input::Configuration;
+
+        // Each reachable tag is a release of the commits since the one before it
+        let dir = scratch_repository("history");
+        let config = Configuration::from_yaml(include_str!("assets/changelog.yml")).unwrap();
+        let history = History::from_tags_with(&config, &GitCommand::at(&dir)).unwrap();
+        let releases: Vec<(&str, &str, Option<&str>)> = history
+            .releases
+            .iter()
+            .map(|r| {
+                let range = r.changelog.range.as_str();
+                (r.title.as_str(), range, r.changelog.version.as_deref())
+            })
+            .collect();
+        assert_eq!(
+            releases,
+            [
+                ("Unreleased", "v0.2.0..HEAD", None),
+                ("v0.2.0", "v0.1.0..v0.2.0", Some("0.2.0")),
+                ("v0.1.0", "v0.1.0", Some("0.1.0")),
+            ]
+        );
+        ::std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,298 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+// Standalone HTML pages
+use changelog::{Change, ChangeLog};
+use commit::{Reference, ReferenceKind};
+use output::{escape, run_post_processors};
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+use regex::Regex;
+use std::collections::HashSet;
+
+/// Render the releases (newest first) as a self-contained HTML page with the given stylesheet.
+///
+/// Every release and titled scope gets an anchor, categories are collapsible and the change text is
+/// post processed and then converted from Markdown (any raw HTML in it is escaped).
+pub fn render_page(
+    releases: &[(&str, &ChangeLog)],
+    stylesheet: &str,
+    processors: &[(Regex, &str)],
+) -> String {
+    let remote = releases
+        .iter()
+        .filter_map(|r| r.1.remote_url.as_ref())
+        .next();
+    let name = remote
+        .and_then(|url| url.rsplit('/').next())
+        .unwrap_or("Project");
+    let mut anchors = HashSet::new();
+
+    let mut page = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n");
+    page.push_str("<meta charset=\"utf-8\">\n");
+    page.push_str(&format!("<title>{} change log</title>\n", escape(name)));
+    page.push_str(&format!("<style>\n{}\n</style>\n", stylesheet.trim()));
+    page.push_str("</head>\n<body>\n");
+    match remote {
+        Some(url) => page.push_str(&format!(
+            "<h1><a href=\"{}\">{}</a> change log</h1>\n",
+            escape(url),
+            escape(name)
+        )),
+        None => page.push_str(&format!("<h1>{} change log</h1>\n", escape(name))),
+    }
+
+    for &(title, clog) in releases {
+        if clog.commits.is_empty() {
+            continue;
+        }
+        let id = anchor(&mut anchors, title);
+        page.push_str(&format!("<section class=\"release\" id=\"{}\">\n", id));
+        page.push_str(&format!(
+            "<h2><a class=\"anchor\" href=\"#{}\">{}</a> <span class=\"date\">{}</span></h2>\n",
+            id,
+            escape(title),
+            escape(&clog.date)
+        ));
+        if let Some(ref url) = clog.compare_url {
+            page.push_str(&format!(
+                "<p class=\"compare\"><a href=\"{}\">Compare changes</a></p>\n",
+                escape(url)
+            ));
+        }
+
+        for scope in &clog.scopes {
+            if scope.title.is_empty() {
+                page.push_str("<section class=\"scope\">\n");
+            } else {
+                let scope_id = anchor(&mut anchors, &format!("{}-{}", title, scope.title));
+                page.push_str(&format!(
+                    "<section class=\"scope\" id=\"{}\">\n<h3><a class=\"anchor\" href=\"#{}\">{}</a></h3>\n",
+                    scope_id,
+                    scope_id,
+                    escape(&scope.title)
+                ));
+            }
+            for category in &scope.categories {
+                page.push_str("<details class=\"category\" open>\n");
+                page.push_str(&format!(
+                    "<summary>{}</summary>\n<ul>\n",
+                    escape(&category.title)
+                ));
+                for change in &category.changes {
+                    let html = change_html(change, clog, processors);
+                    page.push_str(&format!("<li>{}</li>\n", html));
+                }
+                page.push_str("</ul>\n</details>\n");
+            }
+            page.push_str("</section>\n");
+        }
+        page.push_str("</section>\n");
+    }
+
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+/// Render a change (post processed and converted from Markdown) with links to its commit and
+/// references
+fn change_html(change: &Change, clog: &ChangeLog, processors: &[(Regex, &str)]) -> String {
+    let mut text = markdown(&run_post_processors(change.text.trim(), processors));
+    for reference in &change.references {
+        text.push(' ');
+        text.push_str(&reference_html(reference, clog));
+    }
+    if let (Some(url), Some(sha)) = (clog.commit_url.as_ref(), change.sha.get(..7)) {
+        text.push_str(&format!(
+            " <a class=\"sha\" href=\"{}{}\">{}</a>",
+            escape(url),
+            escape(&change.sha),
+            escape(sha)
+        ));
+    }
+    text
+}
+
+/// Render a subject reference, linked if the forge is known
+fn reference_html(reference: &Reference, clog: &ChangeLog) -> String {
+    let (sigil, url) = match reference.kind {
+        ReferenceKind::Pr => ("#", clog.pr_url.as_ref()),
+        ReferenceKind::Mr => ("!", clog.pr_url.as_ref()),
+        ReferenceKind::Issue => ("#", clog.issue_url.as_ref()),
+    };
+    match url {
+        Some(url) => format!(
+            "(<a href=\"{}{}\">{}{}</a>)",
+            escape(url),
+            reference.number,
+            sigil,
+            reference.number
+        ),
+        None => format!("({}{})", sigil, reference.number),
+    }
+}
+
+/// Convert the Markdown text to HTML, escaping any raw HTML in it and leaving out the links and
+/// images whose URLs are not safe (their text stays)
+fn markdown(text: &str) -> String {
+    let mut dropped = Vec::new();
+    let parser = Parser::new_ext(text, Options::ENABLE_STRIKETHROUGH);
+    let events = parser.filter_map(|event| match event {
+        Event::Html(raw) => Some(Event::Text(raw)),
+        Event::Start(Tag::Link(_, ref url, _)) | Event::Start(Tag::Image(_, ref url, _)) => {
+            let safe = safe_url(url);
+            dropped.push(!safe);
+            if safe {
+                Some(event)
+            } else {
+                None
+            }
+        }
+        Event::End(Tag::Link(..)) | Event::End(Tag::Image(..)) => match dropped.pop() {
+            Some(true) => None,
+            _ => Some(event),
+        },
+        event => Some(event),
+    });
+    let mut converted = String::new();
+    html::push_html(&mut converted, events);
+
+    // A single paragraph reads better inline
+    let converted = converted.trim();
+    let inner = converted
+        .strip_prefix("<p>")
+        .and_then(|c| c.strip_suffix("</p>"));
+    match inner {
+        Some(inner) if !inner.contains("<p>") => inner.to_owned(),
+        _ => converted.to_owned(),
+    }
+}
+
+/// Check if the link URL is safe to follow: a relative one, or one with the `http`, `https` or
+/// `mailto` scheme
+fn safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters in the scheme
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(end) if url[end..].starts_with(':') => {
+            let scheme = url[..end].to_lowercase();
+            scheme == "http" || scheme == "https" || scheme == "mailto"
+        }
+        _ => true,
+    }
+}
+
+/// A unique anchor for the given title
+fn anchor(taken: &mut HashSet<String>, title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.to_lowercase().chars() {
+        if c.is_alphanumeric() || c == '.' || c == '_' {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = match slug.trim_end_matches('-') {
+        "" => String::from("section"),
+        slug => slug.to_owned(),
+    };
+
+    let mut unique = slug.clone();
+    let mut n = 1;
+    while !taken.insert(unique.clone()) {
+        n += 1;
+        unique = format!("{}-{}", slug, n);
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{anchor, markdown, render_page};
+    use changelog::ChangeLog;
+    use commit::Commit;
+    use input::Configuration;
+    use regex::Regex;
+    use std::collections::HashSet;
+
+    #[test]
+    fn change_text() {
+        assert_eq!(
+            markdown("Add `Vec<T>` support to <T> and **bold**"),
+            "Add <code>Vec&lt;T&gt;</code> support to &lt;T&gt; and <strong>bold</strong>"
+        );
+        assert_eq!(
+            markdown("<script>alert(1)</script>"),
+            "&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+        assert_eq!(markdown("First\n\nSecond"), "<p>First</p>\n<p>Second</p>");
+    }
+
+    #[test]
+    fn change_links() {
+        // Only web, mail and relative links are kept
+        assert_eq!(
+            markdown("[x](https://x.org) [y](mailto:y@x.org) [z](../z.md#top)"),
+            "<a href=\"https://x.org\">x</a> <a href=\"mailto:y@x.org\">y</a> \
+             <a href=\"../z.md#top\">z</a>"
+        );
+        assert_eq!(markdown("[x](javascript:alert(1))"), "x");
+        assert_eq!(markdown("[x](<JavaScript\t:alert(1)>)"), "x");
+        assert_eq!(markdown("[x](data:text/html,hi) ![y](vbscript:z)"), "x y");
+        assert_eq!(
+            markdown("[![y](javascript:z)](https://x.org)"),
+            "<a href=\"https://x.org\">y</a>"
+        );
+    }
+
+    #[test]
+    fn anchors() {
+        let mut taken = HashSet::new();
+        assert_eq!(anchor(&mut taken, "v1.2.0"), "v1.2.0");
+        assert_eq!(anchor(&mut taken, "v1.2.0 API"), "v1.2.0-api");
+        assert_eq!(anchor(&mut taken, "v1.2.0-API!"), "v1.2.0-api-2");
+        assert_eq!(anchor(&mut taken, "<>"), "section");
+    }
+
+    #[test]
+    fn page() {
+        let config = Configuration::from_yaml(include_str!("assets/changelog.yml")).unwrap();
+        let lines = vec![
+            "1111111aaaaaaa",
+            "Jane",
+            "Sun, 22 Oct 2017 17:26:56 -0400",
+            "Subject (#12)",
+            "- feature: Generic `Filter<T>` for <T>\n- feature(doc): Guide",
+        ];
+        let commit = Commit::from_lines(lines.into_iter().map(String::from).collect());
+        let mut clog = ChangeLog::from(vec![commit].into_iter(), &config);
+        clog.remote_url = Some(String::from("https://github.com/a/b"));
+        clog.commit_url = Some(String::from("https://github.com/a/b/commit/"));
+        clog.pr_url = Some(String::from("https://github.com/a/b/pull/"));
+        // Post processors apply to the change text (before Markdown), not the page
+        let processors = vec![
+            (Regex::new("Guide").unwrap(), "[Guide](https://x.org/guide)"),
+            (Regex::new("body").unwrap(), "BODY"),
+        ];
+        let page = render_page(
+            &[("v1.0", &clog), ("v0.9", &ChangeLog::default())],
+            "body {}",
+            &processors,
+        );
+
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<style>\nbody {}\n</style>"));
+        assert!(page.contains("<section class=\"release\" id=\"v1.0\">"));
+        assert!(page.contains("<section class=\"scope\" id=\"v1.0-documentation\">"));
+        assert!(page.contains("<details class=\"category\" open>\n<summary>Features</summary>"));
+        assert!(page.contains(
+            "<li>Generic <code>Filter&lt;T&gt;</code> for &lt;T&gt; \
+             (<a href=\"https://github.com/a/b/pull/12\">#12</a>) \
+             <a class=\"sha\" href=\"https://github.com/a/b/commit/1111111aaaaaaa\">1111111</a></li>"
+        ));
+        assert!(page.contains("<li><a href=\"https://x.org/guide\">Guide</a> "));
+        assert!(!page.contains("v0.9"));
+    }
+}
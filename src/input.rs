@@ -2,51 +2,153 @@
 // Licensed under the MIT License <https://opensource.org/licenses/MIT>
 
 use super::Result;
-use serde_yaml::from_str;
-use std::env::current_dir;
+use commit::ReferenceKind;
+use error::Error;
+use forge::Forge;
+use glob::{MatchOptions, Pattern};
+use regex::{escape, Regex};
+use serde_yaml::{from_str, from_value, Mapping, Value};
+use std::env::{current_dir, var};
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use workspace::cargo_members;
 
 /// The YAML configuration file name (`.changelog.yml`).
 ///
-/// The library looks for a file with this name in the current directory and all its ancestors (up to root). If
-/// one is found, it is used to initialize configuration, if not the default configuration is used.
+/// The library looks for a file with this name in the repository directory (the current directory,
+/// unless one is given) and all its ancestors (up to root). If one is found, it is used to initialize
+/// configuration, if not the default configuration is used.
 pub const CONFIG_FILE: &str = ".changelog.yml";
 
+/// The TOML configuration file name (`.changelog.toml`).
+///
+/// The library looks for it (and for a `[package.metadata.changelog]` or
+/// `[workspace.metadata.changelog]` table in a `Cargo.toml`) along with
+/// [`CONFIG_FILE`](constant.CONFIG_FILE.html). The closest directory that has any of them wins, and
+/// it is an error for a directory to have more than one.
+pub const TOML_CONFIG_FILE: &str = ".changelog.toml";
+
+/// The user configuration file (`git-changelog/config.yml`), relative to `$XDG_CONFIG_HOME` (or
+/// `~/.config`).
+///
+/// Its configuration is the base that repository configurations override (see
+/// [`Configuration::from_user_dir`](struct.Configuration.html#method.from_user_dir)).
+pub const USER_CONFIG_FILE: &str = "git-changelog/config.yml";
+
+/// The Cargo manifest file name, whose metadata can hold the configuration
+const CARGO_FILE: &str = "Cargo.toml";
+
 /// The embedded configuration used when none is provided by the user.
 const CONFIG_DEFAULT: &str = include_str!("assets/changelog.yml");
 
 /// The Handlebars template file name (`.changelog.hbs`).
 ///
-/// The library looks for a file with this name (`.changelog.hbs`) in the current directory and all its ancestors
-/// (up to root). If one is found, it is used to render the changelog, if not the default template is used.
+/// The library looks for a file with this name (`.changelog.hbs`) in the repository directory (the
+/// current directory, unless one is given) and all its ancestors (up to root). If one is found, it is
+/// used to render the changelog, if not the default template is used.
 pub const TEMPLATE_FILE: &str = ".changelog.hbs";
 
+/// The default pattern that finds release headings (e.g. `# v0.2.0 (2018-01-01)`) in an existing change log.
+pub const RELEASE_HEADING: &str = r"^#\s+(\S+)";
+
 /// The embedded template that is used when none is provided by the user.
 const TEMPLATE_DEFAULT: &str = include_str!("assets/changelog.hbs");
 
+/// The embedded stylesheet of the HTML format, used when none is provided by the user.
+const STYLESHEET_DEFAULT: &str = include_str!("assets/changelog.css");
+
+/// The built-in presets, i.e. a name, a configuration and a template.
+const PRESETS: &[(&str, &str, &str)] = &[
+    ("default", CONFIG_DEFAULT, TEMPLATE_DEFAULT),
+    (
+        "keepachangelog",
+        include_str!("assets/keepachangelog.yml"),
+        include_str!("assets/keepachangelog.hbs"),
+    ),
+];
+
 /// The tool configuration.
 ///
 /// The configuration defines the repository conventions and output preferences.
 #[serde(default)]
-#[derive(Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct Configuration {
+    /// The built-in preset (e.g. `keepachangelog`) the configuration starts from
+    pub preset: Option<String>,
+
     /// The project conventions
     pub conventions: Conventions,
 
     /// The output preferences
     pub output: OutputPreferences,
+
+    /// The policy `git changelog check` enforces
+    pub check: CheckPolicy,
+
+    /// The packages (e.g. the crates of a workspace) that have change logs of their own
+    pub packages: Vec<Package>,
+
+    /// The package the change log is for (picked with `for_package`)
+    #[serde(skip)]
+    pub package: Option<Package>,
+
+    /// The user and repository configuration values, with the name of each source (a preset goes
+    /// under them)
+    #[serde(skip)]
+    sources: Vec<(String, Value)>,
 }
 
 /// The change categorization conventions used by a repository/project.
 #[serde(default)]
-#[derive(Debug, Default, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
 pub struct Conventions {
     /// The scope keywords
     pub scopes: Vec<Keyword>,
 
     /// The category keywords
     pub categories: Vec<Keyword>,
+
+    /// How commit messages are parsed for tagged lines
+    pub parsing: Parsing,
+
+    /// The category tag for breaking changes marked the Conventional Commits way (a `!` after the subject
+    /// type or a `BREAKING CHANGE:` footer)
+    pub breaking: Option<String>,
+
+    /// The patterns that find references on commit subjects (the built-in ones, when empty)
+    pub references: Vec<ReferencePattern>,
+
+    /// The pattern that picks the release tags (its first capture group is the version). Without one,
+    /// the tags that are semantic versions (with an optional `v`) are the releases.
+    pub tag_pattern: Option<String>,
+
+    /// Leave out the pre-release tags (e.g. `v1.0.0-rc.1`)
+    pub skip_prereleases: bool,
+}
+
+/// A pattern that finds references (e.g. `(#123)`) on commit subjects.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct ReferencePattern {
+    /// What the pattern references
+    pub kind: ReferenceKind,
+
+    /// The regular expression that matches the reference (its first capture group is the number)
+    pub pattern: String,
+}
+
+/// The ways to find tagged lines in commit messages.
+#[serde(rename_all = "lowercase")]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+pub enum Parsing {
+    /// Only body lines that start with `- <category>(<scope>):` are tagged.
+    #[default]
+    Lines,
+
+    /// Body lines are tagged as above, and so is a [Conventional Commits](https://www.conventionalcommits.org)
+    /// subject line (e.g. `feat(api)!: add X`) and its `BREAKING CHANGE:` footers.
+    Conventional,
 }
 
 /// A keyword used to categorize commit message lines.
@@ -58,28 +160,171 @@ pub struct Keyword {
 
     /// The presentation title that shows up in the final change log.
     pub title: String,
+
+    /// The version bump that changes with this keyword call for.
+    pub bump: Option<Bump>,
+}
+
+/// The semantic version bumps.
+#[serde(rename_all = "lowercase")]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Bump {
+    /// A bug fix release (`1.2.3` to `1.2.4`)
+    Patch,
+
+    /// A feature release (`1.2.3` to `1.3.0`)
+    Minor,
+
+    /// A breaking release (`1.2.3` to `2.0.0`)
+    Major,
 }
 
 /// The output preferences
 #[serde(default)]
-#[derive(Debug, Default, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
 pub struct OutputPreferences {
-    /// Output as JSON
+    /// The built-in template of the preset, used when no template file is given
+    #[serde(skip)]
+    pub preset_template: Option<&'static str>,
+
+    /// The repository directory the template file is looked for in (along with its ancestors), when
+    /// none is given
+    #[serde(skip)]
+    pub repository: Option<PathBuf>,
+
+    /// The output format (Markdown, unless given)
+    pub format: Option<Format>,
+
+    /// Output as JSON (when the format is not given)
+    #[deprecated(note = "use `format` instead")]
     pub json: bool,
 
     /// Output Handlebar template
     pub template: Option<String>,
 
+    /// The stylesheet file of the HTML format (a built-in one is used when not given)
+    pub stylesheet: Option<String>,
+
     /// The remote url
     pub remote: Option<String>,
 
-    /// Output line post-processors
+    /// The forge that hosts the remote (detected from the remote url when not given)
+    pub forge: Option<Forge>,
+
+    /// Output line post-processors (not applied to Atom feeds)
     pub post_processors: Vec<PostProcessor>,
+
+    /// The pattern that finds release headings in an existing change log (the first capture group is the
+    /// release title). See [`RELEASE_HEADING`](constant.RELEASE_HEADING.html) for the default.
+    pub release_heading: Option<String>,
+
+    /// The package details used by the Debian and RPM formats
+    pub packaging: Packaging,
+}
+
+/// The package details used by the Debian and RPM change log formats.
+#[serde(default)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct Packaging {
+    /// The package name (required by the Debian format)
+    pub name: Option<String>,
+
+    /// The version of the changes that are not tagged yet
+    pub version: Option<String>,
+
+    /// The package revision (e.g. the `1` in `1.2.0-1`)
+    pub revision: Option<String>,
+
+    /// The Debian distribution (`unstable`, unless given)
+    pub distribution: Option<String>,
+
+    /// The Debian urgency (`medium`, unless given)
+    pub urgency: Option<String>,
+
+    /// The maintainer as `Name <email>` (the author of the last commit, unless given)
+    pub maintainer: Option<String>,
+}
+
+/// The policy a revision range must follow to pass `git changelog check`.
+#[serde(default)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct CheckPolicy {
+    /// Every non-merge commit must have at least one tagged line
+    pub require_tags: bool,
+
+    /// Tagged lines the change log drops (unknown tags, no text) are not failures
+    pub allow_dropped: bool,
+
+    /// The changes that must come with others in the same commit
+    pub requires: Vec<Requirement>,
+}
+
+/// A rule that commits with changes in a category must also have a change in a scope (e.g. breaking
+/// changes require a `doc` entry).
+#[serde(default)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct Requirement {
+    /// The category tag that needs the scope
+    pub category: String,
+
+    /// The scope tag that is required
+    pub scope: String,
+}
+
+/// A package that has a change log of its own (built from the commits that touch its paths).
+#[serde(default)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct Package {
+    /// The package name
+    pub name: String,
+
+    /// The path globs (relative to the repository root) of the package files
+    pub paths: Vec<String>,
+
+    /// The prefix of the package release tags (`<name>-`, unless given), followed by the version
+    pub tag_prefix: Option<String>,
+
+    /// Output Handlebar template of the package change log
+    pub template: Option<String>,
+
+    /// The file `--all-packages` writes the package change log to (`CHANGELOG.md` in its first path,
+    /// unless given)
+    pub changelog: Option<String>,
+}
+
+/// The output formats.
+#[serde(rename_all = "lowercase")]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+pub enum Format {
+    /// Markdown rendered with the Handlebars template
+    #[default]
+    Markdown,
+
+    /// JSON data
+    Json,
+
+    /// YAML data
+    Yaml,
+
+    /// Plain text rendered with the Handlebars template (without Markdown headings and links)
+    Text,
+
+    /// A Debian package change log (`debian/changelog`)
+    Debian,
+
+    /// An RPM spec file `%changelog` section
+    Rpm,
+
+    /// An Atom feed with an entry per release
+    Atom,
+
+    /// A standalone HTML page
+    Html,
 }
 
 /// A post-processor definition.
 #[serde(default)]
-#[derive(Debug, Default, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
 pub struct PostProcessor {
     /// The lookup pattern
     pub lookup: String,
@@ -94,17 +339,452 @@ impl Configuration {
         Self::from_file(None).unwrap_or_else(|_| Self::default())
     }
 
-    /// Construct from the given YAML file
+    /// Construct from the given configuration file (YAML, TOML or `Cargo.toml` metadata)
     pub fn from_file(file: Option<&str>) -> Result<Self> {
-        file.map(str::to_owned)
-            .or_else(|| find_file(CONFIG_FILE))
-            .map_or_else(|| Ok(String::from(CONFIG_DEFAULT)), |f| read_file(&f))
-            .and_then(|yml| Self::from_yaml(&yml))
+        let cwd = current_dir().map_err(|e| format_err!("Current directory is invalid: {}", e))?;
+        Self::from_dir(file, &cwd)
+    }
+
+    /// Construct from the given configuration file (YAML, TOML or `Cargo.toml` metadata), looking for the
+    /// configuration and template files in the given repository directory (and its ancestors) when they are not
+    /// explicitly provided.
+    pub fn from_dir(file: Option<&str>, dir: &Path) -> Result<Self> {
+        Self::from_dir_over(None, file, dir)
+    }
+
+    /// Construct like [`from_dir`](#method.from_dir), as the user would run the tool.
+    ///
+    /// The configuration is layered over the user defaults (see
+    /// [`USER_CONFIG_FILE`](constant.USER_CONFIG_FILE.html)) and the `GIT_CHANGELOG_*` environment
+    /// variables override it (e.g. `GIT_CHANGELOG_REMOTE=upstream`).
+    pub fn from_user_dir(file: Option<&str>, dir: &Path) -> Result<Self> {
+        let user = match user_config_file() {
+            Some(file) => Some(single_source(&file, read_sources(Path::new(&file))?)?),
+            None => None,
+        };
+        Self::from_dir_over(user, file, dir)?.with_env(|name| var(name).ok())
+    }
+
+    /// Construct from the configuration file or directory, over the given user configuration
+    fn from_dir_over(
+        user: Option<(String, Value)>,
+        file: Option<&str>,
+        dir: &Path,
+    ) -> Result<Self> {
+        let repo = match file {
+            Some(file) => Some(single_source(file, read_sources(Path::new(file))?)?),
+            None => find_source(dir)?,
+        };
+        let mut config = Self::from_sources(user, repo)?;
+
+        // The repository template is used, unless the configuration names one (or a preset has one)
+        config.output.repository = Some(dir.to_path_buf());
+
+        Ok(config)
     }
 
     /// Construct from the given YAML string
     pub fn from_yaml(yml: &str) -> Result<Self> {
-        from_str(yml).map_err(|e| format_err!("Configuration contains invalid YAML: {}", e))
+        let tree = from_str(yml).map_err(|e| {
+            Error::InvalidConfig(format!("Configuration contains invalid YAML: {}", e))
+        })?;
+        Self::from_sources(None, Some((String::from("YAML"), tree)))
+    }
+
+    /// Construct from the given TOML string
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let tree = toml::from_str(text).map_err(|e| {
+            Error::InvalidConfig(format!("Configuration contains invalid TOML: {}", e))
+        })?;
+        Self::from_sources(None, Some((String::from("TOML"), tree)))
+    }
+
+    /// Construct from the user and repository configurations (the built-in one is used when the repository has
+    /// none), each with the name of its source
+    fn from_sources(user: Option<(String, Value)>, repo: Option<(String, Value)>) -> Result<Self> {
+        let builtin = match repo {
+            Some(_) => None,
+            None => {
+                let tree = from_str(CONFIG_DEFAULT).map_err(|e| {
+                    Error::InvalidConfig(format!("Configuration is invalid: {}", e))
+                })?;
+                Some((String::from("built-in"), tree))
+            }
+        };
+        let sources: Vec<(String, Value)> = user.into_iter().chain(repo).collect();
+        let mut config = Self::from_layers(builtin.into_iter().chain(sources.clone()), None)?;
+        config.sources = sources;
+        Ok(config)
+    }
+
+    /// Construct from the configuration layers (each one overrides the ones before it), putting them
+    /// over the given preset instead of the ones they name (if it is given)
+    fn from_layers<T>(layers: T, over: Option<&str>) -> Result<Self>
+    where
+        T: IntoIterator<Item = (String, Value)>,
+    {
+        let invalid = |e| Error::InvalidConfig(format!("Configuration is invalid: {}", e));
+        let mut layered = match over {
+            Some(name) => {
+                let tree = from_str(preset(name)?.0).map_err(invalid)?;
+                (merge(Value::Null, tree)?, Some(name.to_owned()))
+            }
+            None => (Value::Null, None),
+        };
+        for (source, tree) in layers {
+            layered = extend(layered, tree, &source, over, &mut Vec::new())?;
+        }
+
+        let (tree, preset) = layered;
+        let mut config: Self = from_value(tree).map_err(invalid)?;
+        if let Some(name) = preset {
+            config.output.preset_template = Some(self::preset(&name)?.1);
+            config.preset = Some(name);
+        }
+        Ok(config)
+    }
+
+    /// Override the configuration with the `GIT_CHANGELOG_*` variables of the given environment
+    pub fn with_env<F: Fn(&str) -> Option<String>>(mut self, env: F) -> Result<Self> {
+        if let Some(remote) = env("GIT_CHANGELOG_REMOTE") {
+            self.output.remote = Some(remote);
+        }
+        if let Some(format) = env("GIT_CHANGELOG_FORMAT") {
+            self.output.format = Some(format.parse()?);
+        }
+        if let Some(template) = env("GIT_CHANGELOG_TEMPLATE") {
+            self.output.template = Some(template);
+        }
+        if let Some(stylesheet) = env("GIT_CHANGELOG_STYLESHEET") {
+            self.output.stylesheet = Some(stylesheet);
+        }
+        Ok(self)
+    }
+
+    /// Switch to the conventions and template of the given built-in preset.
+    ///
+    /// The configured conventions are put over the preset (instead of any preset they name), and the
+    /// preset template replaces any configured template file.
+    pub fn with_preset(mut self, name: &str) -> Result<Self> {
+        let preset = Self::from_layers(self.sources.clone(), Some(name))?;
+        self.preset = preset.preset;
+        self.conventions = preset.conventions;
+        self.output.template = None;
+        self.output.preset_template = preset.output.preset_template;
+        self.output.release_heading = preset.output.release_heading;
+        Ok(self)
+    }
+
+    /// Switch to the change log of the given package.
+    ///
+    /// The package tags become the release tags and its template (if any) replaces the configured one.
+    pub fn for_package(self, name: &str) -> Result<Self> {
+        let package = self
+            .packages
+            .iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .ok_or_else(|| Error::InvalidConfig(format!("Unknown package '{}'", name)))?;
+        Ok(self.with_package(package))
+    }
+
+    /// Switch to the change log of the given package (see `for_package`)
+    pub fn with_package(mut self, package: Package) -> Self {
+        let prefix = escape(&package.tag_prefix());
+        self.conventions.tag_pattern = Some(format!("^{}(.+)$", prefix));
+        if package.template.is_some() {
+            self.output.template = package.template.clone();
+        }
+        self.package = Some(package);
+        self
+    }
+
+    /// Add the members of the Cargo workspace in the repository root directory that are not
+    /// configured already
+    pub fn with_cargo_members(mut self, root: &Path) -> Self {
+        for member in cargo_members(root) {
+            if self.packages.iter().all(|p| p.name != member.name) {
+                self.packages.push(member);
+            }
+        }
+        self
+    }
+}
+
+impl Package {
+    /// Get the prefix of the package release tags
+    pub fn tag_prefix(&self) -> String {
+        self.tag_prefix
+            .clone()
+            .unwrap_or_else(|| format!("{}-", self.name))
+    }
+
+    /// Get the `git log` pathspecs that pick the package files (the globs match directories too)
+    pub fn pathspecs(&self) -> Vec<String> {
+        let mut specs = Vec::new();
+        for path in &self.paths {
+            let path = path.trim_end_matches('/');
+            specs.push(format!(":(top,glob){}", path));
+            if !path.ends_with("**") {
+                specs.push(format!(":(top,glob){}/**", path));
+            }
+        }
+        specs
+    }
+
+    /// Check if the file (relative to the repository root) is one of the package files
+    pub fn matches(&self, file: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        self.paths.iter().any(|path| {
+            let path = path.trim_end_matches('/');
+            [path.to_owned(), format!("{}/**", path)]
+                .iter()
+                .filter_map(|p| Pattern::new(p).ok())
+                .any(|p| p.matches_with(file, options))
+        })
+    }
+
+    /// Get the file `--all-packages` writes the package change log to
+    pub fn changelog_file(&self) -> Result<String> {
+        let first = self.paths.first().map(|p| p.trim_end_matches('/'));
+        match (&self.changelog, first) {
+            (Some(file), _) => Ok(file.clone()),
+            (None, Some(path)) if !path.contains(['*', '?', '[']) => {
+                Ok(format!("{}/CHANGELOG.md", path))
+            }
+            _ => Err(Error::InvalidConfig(format!(
+                "Package '{}' needs a `changelog` file (its first path is a glob)",
+                self.name
+            ))
+            .into()),
+        }
+    }
+}
+
+/// Get the configuration and template of the named preset
+fn preset(name: &str) -> Result<(&'static str, &'static str)> {
+    PRESETS
+        .iter()
+        .find(|p| p.0 == name)
+        .map(|p| (p.1, p.2))
+        .ok_or_else(|| Error::InvalidConfig(format!("Unknown preset '{}'", name)).into())
+}
+
+/// Apply the configuration values from the source over the given layer (along with the preset it ends at), after
+/// the layers its `extends` (or `preset`) chain names. The chain has the sources seen so far.
+fn extend(
+    layer: (Value, Option<String>),
+    mut tree: Value,
+    source: &str,
+    over: Option<&str>,
+    chain: &mut Vec<String>,
+) -> Result<(Value, Option<String>)> {
+    let invalid = |why: String| -> ::anyhow::Error { Error::InvalidConfig(why).into() };
+    let take = |tree: &mut Value, key: &str| -> Result<Option<String>> {
+        match tree
+            .as_mapping_mut()
+            .and_then(|m| m.remove(&Value::from(key)))
+        {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(name)) => Ok(Some(name)),
+            Some(_) => Err(invalid(format!(
+                "The `{}` of '{}' is not a name",
+                key, source
+            ))),
+        }
+    };
+    chain.push(source.to_owned());
+
+    // The preset is a base that is always built-in
+    let base = match (take(&mut tree, "preset")?, take(&mut tree, "extends")?) {
+        (Some(_), Some(_)) => {
+            return Err(invalid(format!(
+                "Configuration '{}' has both `preset` and `extends`, keep only one",
+                source
+            )))
+        }
+        (Some(name), None) => Some((name, true)),
+        (None, Some(name)) => Some((name, false)),
+        (None, None) => None,
+    };
+
+    let (tree_below, preset_below) = match base {
+        Some((name, only_preset)) if only_preset || PRESETS.iter().any(|p| p.0 == name) => {
+            let (text, _) = preset(&name)?;
+            if over.is_some() {
+                // The preset picked instead is under all the layers already
+                layer
+            } else {
+                let tree = from_str(text).map_err(|e| invalid(format!("Invalid preset: {}", e)))?;
+                let (tree, _) = extend(layer, tree, &name, over, chain)?;
+                (tree, Some(name))
+            }
+        }
+        Some((name, _)) => {
+            // Paths are relative to the file that names them
+            let dir = Path::new(source).parent().unwrap_or_else(|| Path::new(""));
+            let file = dir.join(&name);
+            let file = file.canonicalize().map_err(|e| {
+                invalid(format!(
+                    "Cannot extend '{}' from '{}' (Reason: {})",
+                    name, source, e
+                ))
+            })?;
+            let file = file.to_string_lossy().into_owned();
+            if chain.contains(&file) {
+                chain.push(file);
+                return Err(invalid(format!(
+                    "Configuration extends itself ({})",
+                    chain.join(" -> ")
+                )));
+            }
+            let (file, tree) = single_source(&file, read_sources(Path::new(&file))?)?;
+            extend(layer, tree, &file, over, chain)?
+        }
+        None => layer,
+    };
+    Ok((merge(tree_below, tree)?, preset_below))
+}
+
+/// Merge the configuration values, the mappings key by key, the keyword lists by their changes (see
+/// `merge_keywords`) and everything else by picking the override
+fn merge(base: Value, over: Value) -> Result<Value> {
+    match (base, over) {
+        (Value::Mapping(mut base), Value::Mapping(over)) => {
+            for (key, value) in over {
+                let existing = base.remove(&key).unwrap_or(Value::Null);
+                let merged = match key.as_str() {
+                    Some("scopes") | Some("categories") => merge_keywords(existing, value)?,
+                    _ => merge(existing, value)?,
+                };
+                base.insert(key, merged);
+            }
+            Ok(Value::Mapping(base))
+        }
+        (Value::Null, over @ Value::Mapping(_)) => merge(Value::Mapping(Mapping::new()), over),
+        (_, over) => Ok(over),
+    }
+}
+
+/// Merge the keyword lists. A list replaces the base, and a mapping changes it: its `remove` tags are dropped,
+/// its `replace` keywords take the place of the ones with the same tag and its `append` keywords are added at
+/// the end.
+fn merge_keywords(base: Value, over: Value) -> Result<Value> {
+    let changes = match over {
+        Value::Mapping(changes) => changes,
+        Value::Null => return Ok(base),
+        over => return Ok(over),
+    };
+    let tag = |keyword: &Value| {
+        keyword
+            .get("tag")
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+    };
+    let list = |key: &str| match changes.get(&Value::from(key)) {
+        Some(Value::Sequence(list)) => Ok(list.clone()),
+        None => Ok(Vec::new()),
+        Some(_) => Err(Error::InvalidConfig(format!(
+            "Keyword `{}` is not a list",
+            key
+        ))),
+    };
+    for (key, _) in changes.iter() {
+        match key.as_str() {
+            Some("remove") | Some("replace") | Some("append") => {}
+            _ => {
+                return Err(Error::InvalidConfig(format!(
+                    "Unknown keyword change {:?} (use `append`, `replace` or `remove`)",
+                    key
+                ))
+                .into())
+            }
+        }
+    }
+
+    let mut keywords = match base {
+        Value::Sequence(keywords) => keywords,
+        _ => Vec::new(),
+    };
+    let removed: Vec<Option<String>> = list("remove")?
+        .iter()
+        .map(|t| t.as_str().map(str::to_owned))
+        .collect();
+    keywords.retain(|k| !removed.contains(&tag(k)));
+    for keyword in list("replace")? {
+        match keywords.iter_mut().find(|k| tag(k) == tag(&keyword)) {
+            Some(existing) => *existing = keyword,
+            None => {
+                let why = format!(
+                    "Cannot replace keyword '{}' (no such tag)",
+                    tag(&keyword).unwrap_or_default()
+                );
+                return Err(Error::InvalidConfig(why).into());
+            }
+        }
+    }
+    for keyword in list("append")? {
+        if keywords.iter().any(|k| tag(k) == tag(&keyword)) {
+            let why = format!(
+                "Cannot append keyword '{}' (the tag is taken)",
+                tag(&keyword).unwrap_or_default()
+            );
+            return Err(Error::InvalidConfig(why).into());
+        }
+        keywords.push(keyword);
+    }
+    Ok(Value::Sequence(keywords))
+}
+
+/// Get the user configuration file, if there is one
+fn user_config_file() -> Option<String> {
+    let home = var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|d| Path::new(d).is_absolute())
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").ok().map(|h| Path::new(&h).join(".config")))?;
+    let file = home.join(USER_CONFIG_FILE);
+    if file.is_file() {
+        Some(file.to_string_lossy().into_owned())
+    } else {
+        None
+    }
+}
+
+impl ReferencePattern {
+    /// The built-in patterns: GitHub style `(#123)` pull requests and GitLab style `(!456)` merge requests
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            ReferencePattern {
+                kind: ReferenceKind::Pr,
+                pattern: String::from(r"\(#(\d+)\)"),
+            },
+            ReferencePattern {
+                kind: ReferenceKind::Mr,
+                pattern: String::from(r"\(!(\d+)\)"),
+            },
+        ]
+    }
+}
+
+impl FromStr for Format {
+    type Err = ::anyhow::Error;
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "markdown" => Ok(Format::Markdown),
+            "json" => Ok(Format::Json),
+            "yaml" => Ok(Format::Yaml),
+            "text" => Ok(Format::Text),
+            "debian" => Ok(Format::Debian),
+            "rpm" => Ok(Format::Rpm),
+            "atom" => Ok(Format::Atom),
+            "html" => Ok(Format::Html),
+            _ => Err(Error::InvalidConfig(format!("Unknown output format '{}'", name)).into()),
+        }
     }
 }
 
@@ -119,6 +799,29 @@ impl Conventions {
         self.title(&self.categories, category)
     }
 
+    /// Check that the patterns in the conventions are valid regular expressions
+    pub fn validate(&self) -> ::std::result::Result<(), Error> {
+        let patterns = self.references.iter().map(|r| &r.pattern);
+        for pattern in patterns.chain(self.tag_pattern.as_ref()) {
+            if let Err(why) = Regex::new(pattern) {
+                return Err(Error::InvalidConfig(format!(
+                    "Invalid pattern '{}' (Reason: {})",
+                    pattern, why
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the patterns that find references on commit subjects
+    pub fn reference_patterns(&self) -> Vec<ReferencePattern> {
+        if self.references.is_empty() {
+            ReferencePattern::defaults()
+        } else {
+            self.references.clone()
+        }
+    }
+
     /// Get the titles for all the categories defined
     pub fn category_titles(&self) -> Vec<&str> {
         Self::titles(&self.categories)
@@ -163,6 +866,7 @@ impl Keyword {
         Keyword {
             tag: tag.as_ref().to_owned(),
             title: title.as_ref().to_owned(),
+            bump: None,
         }
     }
 }
@@ -173,13 +877,42 @@ impl OutputPreferences {
         Self::default()
     }
 
+    /// Get the output format (the deprecated `json` flag picks JSON when no format is given)
+    #[allow(deprecated)]
+    pub fn get_format(&self) -> Format {
+        match self.format {
+            Some(format) => format,
+            None if self.json => Format::Json,
+            None => Format::default(),
+        }
+    }
+
+    /// Get the release heading pattern
+    pub fn get_release_heading(&self) -> &str {
+        self.release_heading.as_deref().unwrap_or(RELEASE_HEADING)
+    }
+
     /// Get the template definition
     pub fn get_template(&self) -> Result<String> {
+        if let (None, Some(template)) = (&self.template, self.preset_template) {
+            return Ok(String::from(template));
+        }
         self.template
             .clone()
-            .or_else(|| find_file(TEMPLATE_FILE))
+            .or_else(|| {
+                self.repository
+                    .as_ref()
+                    .and_then(|d| find_file(d, TEMPLATE_FILE))
+            })
             .map_or_else(|| Ok(String::from(TEMPLATE_DEFAULT)), |f| read_file(&f))
     }
+
+    /// Get the stylesheet of the HTML format
+    pub fn get_stylesheet(&self) -> Result<String> {
+        self.stylesheet
+            .as_ref()
+            .map_or_else(|| Ok(String::from(STYLESHEET_DEFAULT)), |f| read_file(f))
+    }
 }
 
 /// Read the given file to a String (with logging)
@@ -196,10 +929,95 @@ fn read_file(name: &str) -> Result<String> {
     Ok(contents)
 }
 
+/// Read the configurations in the file (a Cargo manifest has one in each `metadata.changelog` table), along with
+/// the names of their sources
+fn read_sources(file: &Path) -> Result<Vec<(String, Value)>> {
+    let name = file.to_string_lossy().into_owned();
+    let text = read_file(&name)?;
+    let toml = |text: &str| -> Result<Value> {
+        toml::from_str(text).map_err(|e| {
+            Error::InvalidConfig(format!("File '{}' contains invalid TOML: {}", name, e)).into()
+        })
+    };
+    if file.file_name() == Some(CARGO_FILE.as_ref()) {
+        let manifest = toml(&text)?;
+        let mut sources = Vec::new();
+        for table in &["package", "workspace"] {
+            let metadata = manifest
+                .get(table)
+                .and_then(|t| t.get("metadata"))
+                .and_then(|m| m.get("changelog"));
+            if let Some(metadata) = metadata {
+                let source = format!("{} [{}.metadata.changelog]", name, table);
+                sources.push((source, metadata.clone()));
+            }
+        }
+        Ok(sources)
+    } else if file.extension() == Some("toml".as_ref()) {
+        Ok(vec![(name.clone(), toml(&text)?)])
+    } else {
+        let tree = from_str(&text).map_err(|e| {
+            Error::InvalidConfig(format!("File '{}' contains invalid YAML: {}", name, e))
+        })?;
+        Ok(vec![(name.clone(), tree)])
+    }
+}
+
+/// Pick the only configuration of the given sources (more than one is a conflict)
+fn single_source(file: &str, mut sources: Vec<(String, Value)>) -> Result<(String, Value)> {
+    match sources.len() {
+        0 => Err(Error::InvalidConfig(format!("File '{}' has no configuration", file)).into()),
+        1 => Ok(sources.remove(0)),
+        _ => Err(conflict(&sources)),
+    }
+}
+
+/// Report the sources that conflict
+fn conflict(sources: &[(String, Value)]) -> ::anyhow::Error {
+    let names: Vec<String> = sources.iter().map(|s| format!("'{}'", s.0)).collect();
+    let why = format!(
+        "Configuration is in more than one place ({}), keep only one",
+        names.join(", ")
+    );
+    Error::InvalidConfig(why).into()
+}
+
+/// Find the closest configuration: the YAML or TOML file or the `Cargo.toml` metadata in the given directory or
+/// its ancestors (a directory with more than one of these is a conflict)
+fn find_source(dir: &Path) -> Result<Option<(String, Value)>> {
+    let mut cwd = match dir.canonicalize() {
+        Ok(cwd) => cwd,
+        Err(_) => return Ok(None),
+    };
+    loop {
+        let mut sources = Vec::new();
+        for name in &[CONFIG_FILE, TOML_CONFIG_FILE, CARGO_FILE] {
+            let file = cwd.join(name);
+            if !file.is_file() {
+                continue;
+            }
+            match read_sources(&file) {
+                Ok(found) => sources.extend(found),
+                // Cargo manifests that have no valid configuration are not ours to report
+                Err(why) if *name == CARGO_FILE => debug!("{} (ignored)", why),
+                Err(why) => return Err(why),
+            }
+        }
+        match sources.len() {
+            0 => {}
+            1 => return Ok(sources.pop()),
+            _ => return Err(conflict(&sources)),
+        }
+        if !cwd.pop() {
+            return Ok(None);
+        }
+    }
+}
+
 /// Identify the closest configuration file that should be used for this run
-fn find_file(file: &str) -> Option<String> {
-    // Start at the current directory
-    let mut cwd = current_dir().expect("Current directory is invalid");
+fn find_file(dir: &Path, file: &str) -> Option<String> {
+    // Start at the given directory
+    let mut cwd = dir.canonicalize().ok()?;
 
     // While we have hope
     while cwd.exists() {
@@ -250,10 +1068,360 @@ mod tests {
         assert!(Configuration::from_yaml(no_category).is_ok());
     }
 
+    #[test]
+    fn parsing_from_yaml() {
+        use super::Parsing;
+        let conventional = r#"
+        conventions:
+          parsing: conventional
+          breaking: break
+        "#;
+        let config = Configuration::from_yaml(conventional).unwrap();
+        assert_eq!(config.conventions.parsing, Parsing::Conventional);
+        assert_eq!(config.conventions.breaking, Some(String::from("break")));
+        let config = Configuration::from_yaml("conventions: {}").unwrap();
+        assert_eq!(config.conventions.parsing, Parsing::Lines);
+        assert!(Configuration::from_yaml("conventions: {parsing: unknown}").is_err());
+    }
+
+    #[test]
+    fn presets() {
+        let config = Configuration::from_yaml("preset: keepachangelog").unwrap();
+        assert_eq!(
+            config.conventions.category_title(Some(String::from("fix"))),
+            Some("Fixed")
+        );
+        assert!(config
+            .output
+            .get_template()
+            .unwrap()
+            .contains("Keep a Changelog"));
+        assert!(config.output.release_heading.is_some());
+
+        // The configuration overrides the preset
+        let config = Configuration::from_yaml(
+            "preset: keepachangelog\noutput: {remote: upstream}\nconventions: {categories: []}",
+        )
+        .unwrap();
+        assert!(config.conventions.categories.is_empty());
+        assert_eq!(config.conventions.breaking, Some(String::from("break")));
+        assert_eq!(config.output.remote, Some(String::from("upstream")));
+
+        let config = Configuration::from_yaml("conventions: {}").unwrap();
+        let config = config.with_preset("keepachangelog").unwrap();
+        assert_eq!(config.preset, Some(String::from("keepachangelog")));
+        assert!(config
+            .output
+            .get_template()
+            .unwrap()
+            .contains("Keep a Changelog"));
+        assert!(Configuration::from_yaml("preset: unknown").is_err());
+
+        // The configured conventions are kept over the preset
+        let yml = "conventions: {categories: {append: [{tag: perf, title: Speed}]}}";
+        let config = Configuration::from_yaml(yml).unwrap();
+        let config = config.with_preset("keepachangelog").unwrap();
+        assert_eq!(
+            config
+                .conventions
+                .category_title(Some(String::from("perf"))),
+            Some("Speed")
+        );
+        assert_eq!(
+            config.conventions.category_title(Some(String::from("fix"))),
+            Some("Fixed")
+        );
+    }
+
+    #[test]
+    fn packages() {
+        use super::Package;
+        let yml = r#"
+packages:
+  - {name: cli, paths: [crates/cli/], template: cli.hbs}
+  - {name: core, paths: ["crates/core/**"], tag_prefix: "core@"}
+"#;
+        let config = Configuration::from_yaml(yml).unwrap();
+        assert_eq!(config.packages.len(), 2);
+        assert!(config.package.is_none());
+
+        let cli = Configuration::from_yaml(yml)
+            .unwrap()
+            .for_package("cli")
+            .unwrap();
+        assert_eq!(
+            cli.conventions.tag_pattern,
+            Some(String::from("^cli\\-(.+)$"))
+        );
+        assert_eq!(cli.output.template, Some(String::from("cli.hbs")));
+        assert_eq!(
+            cli.package.unwrap().pathspecs(),
+            [":(top,glob)crates/cli", ":(top,glob)crates/cli/**"]
+        );
+
+        let core = config.for_package("core").unwrap();
+        assert_eq!(
+            core.conventions.tag_pattern,
+            Some(String::from("^core@(.+)$"))
+        );
+        assert_eq!(core.output.template, None);
+        assert_eq!(
+            core.package.unwrap().pathspecs(),
+            [":(top,glob)crates/core/**"]
+        );
+
+        let config = Configuration::from_yaml(yml).unwrap();
+        assert!(config.for_package("unknown").is_err());
+
+        // Files match the paths and everything under them
+        let package = |paths: &[&str], changelog: Option<&str>| Package {
+            name: String::from("x"),
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            changelog: changelog.map(String::from),
+            ..Package::default()
+        };
+        let glob = package(&["crates/*/src", "README.md"], None);
+        assert!(glob.matches("crates/a/src/lib.rs"));
+        assert!(glob.matches("crates/a/src/bin/main.rs"));
+        assert!(glob.matches("README.md"));
+        assert!(!glob.matches("crates/a/Cargo.toml"));
+        assert!(!glob.matches("crates/a/b/src/lib.rs"));
+        assert!(glob.changelog_file().is_err());
+        let dir = package(&["crates/cli/"], None);
+        assert_eq!(dir.changelog_file().unwrap(), "crates/cli/CHANGELOG.md");
+        let file = package(&["crates/*/src"], Some("NEWS.md"));
+        assert_eq!(file.changelog_file().unwrap(), "NEWS.md");
+    }
+
+    #[test]
+    fn configuration_from_toml() {
+        let yml = "preset: keepachangelog\noutput: {format: json}\nconventions:\n  scopes: [{tag: cli, title: CLI}]\n";
+        let toml = "preset = \"keepachangelog\"\n[output]\nformat = \"json\"\n[[conventions.scopes]]\ntag = \"cli\"\ntitle = \"CLI\"\n";
+        let (yml, toml) = (
+            Configuration::from_yaml(yml).unwrap(),
+            Configuration::from_toml(toml).unwrap(),
+        );
+        assert_eq!(yml.conventions, toml.conventions);
+        assert_eq!(yml.output, toml.output);
+        assert!(Configuration::from_toml("output = [").is_err());
+    }
+
+    #[test]
+    fn configuration_sources() {
+        use std::env::temp_dir;
+        use std::fs;
+        use std::path::Path;
+        let dir = temp_dir().join(format!("changelog-sources-{}", ::std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let write = |file: &str, text: &str| fs::write(dir.join(file), text).unwrap();
+        let scopes = |dir: &Path| {
+            Configuration::from_dir(None, dir).map(|c| {
+                let tags: Vec<String> = c.conventions.scopes.into_iter().map(|k| k.tag).collect();
+                tags.join(" ")
+            })
+        };
+
+        // The Cargo manifest metadata is a source
+        let manifest = "[package]\nname = \"x\"\n[package.metadata.changelog]\nconventions = {scopes = [{tag = \"cargo\"}]}\n";
+        write("Cargo.toml", manifest);
+        assert_eq!(scopes(&dir.join("sub")).unwrap(), "cargo");
+
+        // The closest directory wins
+        write(
+            "sub/.changelog.toml",
+            "[[conventions.scopes]]\ntag = \"toml\"\n",
+        );
+        assert_eq!(scopes(&dir.join("sub")).unwrap(), "toml");
+
+        // A directory with two sources is a conflict
+        write(".changelog.yml", "conventions: {scopes: [{tag: yml}]}");
+        let why = scopes(&dir).unwrap_err().to_string();
+        assert!(why.contains(".changelog.yml") && why.contains("package.metadata.changelog"));
+
+        // The given file picks the source
+        let file = dir.join("Cargo.toml");
+        let config = Configuration::from_dir(file.to_str(), &dir).unwrap();
+        assert_eq!(config.conventions.scopes[0].tag, "cargo");
+        write("Cargo.toml", "[package]\nname = \"x\"\n");
+        assert!(Configuration::from_dir(file.to_str(), &dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn configuration_extends() {
+        use super::Format;
+        use std::env::temp_dir;
+        use std::fs;
+        let dir = temp_dir().join(format!("changelog-extends-{}", ::std::process::id()));
+        fs::create_dir_all(dir.join("shared")).unwrap();
+        let write = |file: &str, text: &str| fs::write(dir.join(file), text).unwrap();
+        let file = dir.join(".changelog.yml");
+        let read = || Configuration::from_dir(file.to_str(), &dir);
+
+        // A file extends a preset, and another file extends it (with a path relative to it)
+        write(
+            "shared/base.yml",
+            "extends: keepachangelog
+output: {remote: upstream, format: json}",
+        );
+        write(
+            ".changelog.yml",
+            "extends: shared/base.yml
+output: {format: yaml}",
+        );
+        let config = read().unwrap();
+        assert_eq!(config.preset, Some(String::from("keepachangelog")));
+        assert_eq!(config.output.remote, Some(String::from("upstream")));
+        assert_eq!(config.output.format, Some(Format::Yaml));
+        assert_eq!(config.conventions.breaking, Some(String::from("break")));
+
+        // Unknown bases and cycles are errors
+        write(".changelog.yml", "extends: shared/none.yml");
+        assert!(read().is_err());
+        write("shared/base.yml", "extends: ../.changelog.yml");
+        write(".changelog.yml", "extends: shared/base.yml");
+        assert!(read().unwrap_err().to_string().contains("extends itself"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(Configuration::from_yaml(
+            "extends: default
+preset: default"
+        )
+        .is_err());
+        assert!(Configuration::from_yaml("extends: keepachangelog").is_ok());
+    }
+
+    #[test]
+    fn keyword_changes() {
+        let tags = |yml: &str| {
+            Configuration::from_yaml(yml).map(|c| {
+                let tags: Vec<String> = c
+                    .conventions
+                    .categories
+                    .into_iter()
+                    .map(|k| k.tag)
+                    .collect();
+                tags.join(" ")
+            })
+        };
+        let base = "extends: keepachangelog
+conventions:
+  categories:
+";
+        let changes = "    remove: [add, feature, changed, change, deprecated, deprecate, removed, remove, fixed, security, nope]
+    replace: [{tag: fix, title: Bugs}]
+    append: [{tag: perf, title: Speed}]
+";
+        assert_eq!(
+            tags(&format!("{}{}", base, changes)).unwrap(),
+            "added break fix perf"
+        );
+        let config = Configuration::from_yaml(&format!("{}{}", base, changes)).unwrap();
+        assert_eq!(config.conventions.categories[2].title, "Bugs");
+
+        // A list replaces the base, no changes keep it
+        assert_eq!(tags(&format!("{}    - {{tag: a}}", base)).unwrap(), "a");
+        assert_eq!(tags(base).unwrap().split(' ').count(), 13);
+
+        // The changes have to make sense
+        assert!(tags(&format!("{}    append: [{{tag: fix}}]", base)).is_err());
+        assert!(tags(&format!("{}    replace: [{{tag: nope}}]", base)).is_err());
+        assert!(tags(&format!("{}    insert: [{{tag: a}}]", base)).is_err());
+    }
+
+    #[test]
+    fn configuration_layers() {
+        use super::Format;
+        use serde_yaml::from_str;
+        let source = |yml: &str| Some((String::from("test"), from_str(yml).unwrap()));
+        let user = "output: {remote: upstream}
+conventions: {categories: {append: [{tag: mine}]}}";
+
+        // The user defaults override the built-in configuration and the repository overrides both
+        let config = Configuration::from_sources(source(user), None).unwrap();
+        assert_eq!(config.output.remote, Some(String::from("upstream")));
+        assert_eq!(config.conventions.categories.last().unwrap().tag, "mine");
+        let repo = "output: {remote: origin}
+conventions: {categories: [{tag: fix}]}";
+        let config = Configuration::from_sources(source(user), source(repo)).unwrap();
+        assert_eq!(config.output.remote, Some(String::from("origin")));
+        assert_eq!(config.conventions.categories.len(), 1);
+
+        // The environment overrides them all
+        let env = |name: &str| match name {
+            "GIT_CHANGELOG_REMOTE" => Some(String::from("fork")),
+            "GIT_CHANGELOG_FORMAT" => Some(String::from("json")),
+            _ => None,
+        };
+        let config = config.with_env(env).unwrap();
+        assert_eq!(config.output.remote, Some(String::from("fork")));
+        assert_eq!(config.output.format, Some(Format::Json));
+        let bad = |name: &str| Some(name.to_owned());
+        assert!(Configuration::new().with_env(bad).is_err());
+    }
+
+    #[test]
+    fn format_from_yaml() {
+        use super::Format;
+        let format = |yml: &str| Configuration::from_yaml(yml).map(|c| c.output.get_format());
+        assert_eq!(format("output: {}").unwrap(), Format::Markdown);
+        assert_eq!(format("output: {format: yaml}").unwrap(), Format::Yaml);
+        assert_eq!(format("output: {json: true}").unwrap(), Format::Json);
+        assert_eq!(format("output: {json: false}").unwrap(), Format::Markdown);
+        assert_eq!(
+            format("output: {json: true, format: yaml}").unwrap(),
+            Format::Yaml
+        );
+        assert_eq!(
+            format("output: {json: true, format: json}").unwrap(),
+            Format::Json
+        );
+        assert_eq!(
+            format("output: {json: true, format: markdown}").unwrap(),
+            Format::Markdown
+        );
+        assert!(format("output: {format: unknown}").is_err());
+        assert_eq!("text".parse::<Format>().unwrap(), Format::Text);
+        assert!("unknown".parse::<Format>().is_err());
+    }
+
     #[test]
     fn find_file() {
         use super::find_file;
-        assert!(find_file("unknown").is_none());
-        assert!(find_file("Cargo.toml").is_some());
+        use std::path::Path;
+        assert!(find_file(Path::new("."), "unknown").is_none());
+        assert!(find_file(Path::new("."), "Cargo.toml").is_some());
+        assert!(find_file(Path::new("src/assets"), "Cargo.toml").is_some());
+        assert!(find_file(Path::new("no-such-dir"), "Cargo.toml").is_none());
+    }
+
+    #[test]
+    fn configuration_from_dir() {
+        use std::path::Path;
+        let config = Configuration::from_dir(None, Path::new("src/assets")).unwrap();
+        assert_eq!(config.conventions.categories.len(), 2);
+        assert!(Configuration::from_dir(Some("unknown"), Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn template_from_dir() {
+        use std::env::temp_dir;
+        use std::fs;
+
+        // The template is found in the repository directory, not the current one
+        let dir = temp_dir().join(format!("changelog-template-{}", ::std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join(super::TEMPLATE_FILE), "{{range}}").unwrap();
+        let config = Configuration::from_dir(None, &dir.join("sub")).unwrap();
+        let template = config.output.get_template();
+        let preset = config.with_preset("keepachangelog").unwrap();
+        let preset = preset.output.get_template();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(template.unwrap(), "{{range}}");
+        assert_ne!(preset.unwrap(), "{{range}}");
+        assert_eq!(
+            super::OutputPreferences::default().get_template().unwrap(),
+            super::TEMPLATE_DEFAULT
+        );
     }
 }
@@ -70,7 +70,7 @@
 //! these as follows:
 //!
 //! ```rust
-//! use changelog::{Configuration, Keyword, ChangeLog};
+//! use changelog::{Configuration, Format, Keyword, ChangeLog};
 //!
 //! // Create a custom configuration
 //! let mut config = Configuration::new();
@@ -86,12 +86,25 @@
 //! let changelog = ChangeLog::from_range(range, &config);
 //!
 //! // Pick output preferences
-//! config.output.json = true;
+//! config.output.format = Some(Format::Json);
 //!
 //! // Render
 //! assert!(changelog::render(&changelog, &config.output).is_ok());
 //! ```
 //!
+//! Git failures (e.g. a misspelled range) are logged and leave the change log empty. The fallible
+//! constructors report them as an [`Error`](enum.Error.html) instead:
+//!
+//! ```rust
+//! use changelog::{ChangeLog, Configuration, Error};
+//!
+//! let range = vec![String::from("no-such-tag..HEAD")];
+//! match ChangeLog::try_from_log(range, &Configuration::new()) {
+//!     Err(Error::BadRevision(why)) => println!("Check the range: {}", why),
+//!     other => panic!("Unexpected {:?}", other),
+//! }
+//! ```
+//!
 //! # Crate features
 //!
 //! There are two features you can disable when depending on the crate as a library,
@@ -100,6 +113,13 @@
 //! - `bin`: Required to compile the standalone executable target
 //! - `handlebars`: Required to render Handlebars templates with `changelog::render`
 //!
+//! There are also opt-in features:
+//!
+//! - `html`: Renders standalone HTML pages with `changelog::render` (and `--format html`).
+//! - `libgit2`: Provides `changelog::LibGit2`, a [`GitBackend`](trait.GitBackend.html) that reads
+//!   the repository without a `git` executable on `PATH`. Pass it to `ChangeLog::from_log_with`
+//!   (the `git-changelog` executable always runs `git`).
+//!
 //! To control which of these you take, you can use the following alternative in your Cargo.toml:
 //!
 //! ```toml
@@ -115,40 +135,94 @@
 extern crate chrono;
 #[macro_use]
 extern crate anyhow;
+#[cfg(feature = "libgit2")]
+extern crate git2;
+extern crate glob;
 #[cfg(feature = "handlebars")]
 extern crate handlebars;
 #[macro_use]
 extern crate log;
 #[macro_use]
 extern crate nom;
+#[cfg(feature = "html")]
+extern crate pulldown_cmark;
 extern crate regex;
+#[cfg(test)]
+extern crate roxmltree;
+extern crate semver;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
 extern crate serde_yaml;
+extern crate toml;
 
 mod changelog;
+mod check;
 mod commit;
+mod error;
+mod feed;
+mod forge;
 mod git;
+#[cfg(feature = "libgit2")]
+mod git_libgit2;
+mod history;
+#[cfg(feature = "html")]
+mod html;
 mod input;
+mod lint;
 mod output;
 #[cfg(feature = "handlebars")]
 mod template_hbs;
+mod version;
+mod workspace;
 
 pub use changelog::Category;
+pub use changelog::Change;
 pub use changelog::ChangeLog;
+pub use changelog::Contributor;
 pub use changelog::Scope;
+pub use check::Audit;
+pub use check::AuditedCommit;
 pub use commit::Commit;
 pub use commit::CommitList;
 pub use commit::CommitMessage;
+pub use commit::Reference;
+pub use commit::ReferenceKind;
+pub use error::Error;
+pub use forge::Forge;
 pub use git::in_git_repository;
+pub use git::ChangedFiles;
+pub use git::GitBackend;
+pub use git::GitCommand;
+#[cfg(feature = "libgit2")]
+pub use git_libgit2::LibGit2;
+pub use history::History;
+pub use history::Release;
+pub use input::Bump;
+pub use input::CheckPolicy;
 pub use input::Configuration;
 pub use input::Conventions;
+pub use input::Format;
 pub use input::Keyword;
 pub use input::OutputPreferences;
+pub use input::Package;
+pub use input::Packaging;
+pub use input::Parsing;
 pub use input::PostProcessor;
+pub use input::ReferencePattern;
+pub use input::Requirement;
 pub use input::CONFIG_FILE;
+pub use input::RELEASE_HEADING;
 pub use input::TEMPLATE_FILE;
+pub use input::TOML_CONFIG_FILE;
+pub use input::USER_CONFIG_FILE;
+pub use lint::lint_message;
+pub use lint::Problem;
 pub use output::render;
+pub use output::render_history;
+pub use output::update;
+pub use workspace::PackageLog;
+pub use workspace::Workspace;
 
 pub type Result<T> = std::result::Result<T, anyhow::Error>;
@@ -0,0 +1,207 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+// Commit message checks
+use commit::{parse_breaking_footer, parse_conventional_subject, parse_line, Line};
+use input::{Conventions, Keyword, Parsing};
+use std::fmt;
+
+/// The line git puts above the diff in verbose commit messages (everything below it is ignored)
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// A problem found in a commit message.
+#[derive(Debug, Eq, PartialEq, Serialize)]
+pub struct Problem {
+    /// The (1-based) line number in the message
+    pub line: usize,
+
+    /// What is wrong
+    pub description: String,
+
+    /// A known tag close to the unknown one, if any
+    pub suggestion: Option<String>,
+}
+
+/// Check the tagged lines in the commit message against the conventions.
+///
+/// Reports the lines that would be quietly dropped from the change log: unknown categories, unknown
+/// scopes and tags with no change text. Comment lines (starting with `#`) are ignored, like git does.
+pub fn lint_message(message: &str, conventions: &Conventions) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let conventional = conventions.parsing == Parsing::Conventional;
+
+    // Pick the lines git would keep, with their line numbers
+    let mut lines = message
+        .lines()
+        .take_while(|line| *line != SCISSORS)
+        .enumerate()
+        .map(|(n, line)| (n + 1, line))
+        .filter(|&(_, line)| !line.starts_with('#'));
+
+    // With Conventional Commits, the subject is a tagged line
+    if let Some((n, subject)) = lines.next() {
+        if conventional {
+            if let Some(line) = parse_conventional_subject(subject) {
+                check(n, line, conventions, &mut problems);
+            }
+        }
+    }
+
+    // The running current tagged line (and where it started)
+    let mut current: Option<(usize, Line)> = None;
+
+    for (n, text) in lines {
+        // With Conventional Commits, breaking change footers are tagged lines too
+        let line = match parse_breaking_footer(text) {
+            Some(footer) if conventional => footer,
+            _ => parse_line(text),
+        };
+
+        if line.category.is_some() || line.breaking {
+            // Close the current tagged line and start a new one
+            if let Some((start, tagged)) = current.take() {
+                check(start, tagged, conventions, &mut problems);
+            }
+            current = Some((n, line));
+        } else if let Some((_, ref mut tagged)) = current {
+            // Untagged lines continue the current one
+            let more = line.text.unwrap_or_default();
+            tagged.text = Some(tagged.text.take().unwrap_or_default() + "\n" + &more);
+        }
+    }
+
+    // Close the last one
+    if let Some((start, tagged)) = current {
+        check(start, tagged, conventions, &mut problems);
+    }
+
+    problems
+}
+
+/// Check a single tagged line, recording any problems found
+fn check(n: usize, line: Line, conventions: &Conventions, problems: &mut Vec<Problem>) {
+    let mut problem = |description: String, suggestion: Option<String>| {
+        problems.push(Problem {
+            line: n,
+            description,
+            suggestion,
+        })
+    };
+
+    // Breaking changes go to the breaking category, if one is configured
+    let category = match conventions.breaking {
+        Some(ref breaking) if line.breaking => Some(breaking.to_owned()),
+        _ => line.category,
+    };
+
+    if let Some(category) = category {
+        if conventions.category_title(Some(category.clone())).is_none() {
+            let suggestion = closest(&category, &conventions.categories);
+            problem(format!("unknown category '{}'", category), suggestion);
+        }
+    }
+
+    if conventions.scope_title(line.scope.clone()).is_none() {
+        match line.scope {
+            Some(scope) => {
+                let suggestion = closest(&scope, &conventions.scopes);
+                problem(format!("unknown scope '{}'", scope), suggestion);
+            }
+            None => problem(String::from("missing scope"), None),
+        }
+    }
+
+    if !matches!(line.text, Some(ref text) if !text.trim().is_empty()) {
+        problem(String::from("tag with no change text"), None);
+    }
+}
+
+/// Find the keyword tag closest to the given one (if it is close enough to be a likely typo)
+fn closest(tag: &str, keywords: &[Keyword]) -> Option<String> {
+    keywords
+        .iter()
+        .filter(|kw| !kw.tag.is_empty())
+        .map(|kw| (distance(tag, &kw.tag), &kw.tag))
+        .filter(|&(d, kw)| d <= 2 && d < kw.chars().count())
+        .min_by_key(|&(d, _)| d)
+        .map(|(_, kw)| kw.to_owned())
+}
+
+/// The edit (Levenshtein) distance between two strings
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.description)?;
+        if let Some(ref suggestion) = self.suggestion {
+            write!(f, " (did you mean '{}'?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lint_message;
+    use input::{Configuration, Parsing};
+
+    #[test]
+    fn lint() {
+        let config = Configuration::from_yaml(include_str!("assets/changelog.yml")).unwrap();
+        let conventions = &config.conventions;
+        let problems = |message: &str| -> Vec<String> {
+            lint_message(message, conventions)
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+
+        // Good messages
+        assert!(problems("Subject\n\n- fix: foo\n- feature(api):\n  bar\n\nuntagged").is_empty());
+        assert!(problems("Subject\n# - fxi: a comment").is_empty());
+
+        // Typos, unknown tags and empty tags
+        let message = "Subject\n\n- fxi: foo\n- fix(apu): bar\n- break:\n\n- nope(doc): baz";
+        assert_eq!(
+            problems(message),
+            vec![
+                "line 3: unknown category 'fxi' (did you mean 'fix'?)",
+                "line 4: unknown scope 'apu' (did you mean 'api'?)",
+                "line 5: tag with no change text",
+                "line 7: unknown category 'nope'",
+            ]
+        );
+
+        // Conventional Commits subjects
+        let mut config = Configuration::from_yaml(include_str!("assets/changelog.yml")).unwrap();
+        config.conventions.parsing = Parsing::Conventional;
+        let found = lint_message("fx(api): foo\n\nBREAKING CHANGE: bar", &config.conventions);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].to_string(),
+            "line 1: unknown category 'fx' (did you mean 'fix'?)"
+        );
+    }
+
+    #[test]
+    fn distance() {
+        use super::distance;
+        assert_eq!(distance("fix", "fix"), 0);
+        assert_eq!(distance("fxi", "fix"), 2);
+        assert_eq!(distance("featur", "feature"), 1);
+        assert_eq!(distance("", "api"), 3);
+    }
+}
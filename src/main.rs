@@ -5,20 +5,25 @@
 extern crate changelog;
 #[macro_use]
 extern crate clap;
+#[macro_use]
 extern crate anyhow;
 extern crate console;
 extern crate env_logger;
 #[macro_use]
 extern crate log;
+extern crate serde_json;
 
-use changelog::{ChangeLog, Configuration, Result};
+use changelog::{
+    Audit, ChangeLog, Configuration, Format, GitBackend, GitCommand, History, Result, Workspace,
+};
 use clap::{App, AppSettings};
 use console::style;
 use env_logger::{fmt::Formatter, Builder};
 use log::{LevelFilter, Record};
 use std::env::args_os;
 use std::ffi::OsString;
-use std::io::Write;
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
 use std::process::exit;
 
 /// The entry-point.
@@ -44,13 +49,28 @@ fn run(args: Vec<OsString>) -> Result<String> {
     initialize_logging(cli.occurrences_of("debug"));
 
     // Ensure we're in a git directory
-    changelog::in_git_repository()?;
+    let git = GitCommand::at(cli.value_of("repo").unwrap_or("."));
+    git.in_git_repository()?;
 
     // Initialize the tool configuration
-    let mut config = Configuration::from_file(cli.value_of("config"))?;
+    let mut config = Configuration::from_user_dir(cli.value_of("config"), git.root())?;
 
     // Pick overrides from the command line
-    config.output.json = cli.is_present("json");
+    if let Some(preset) = cli.value_of("preset") {
+        config = config.with_preset(preset)?;
+    }
+    if cli.is_present("package") || cli.is_present("all-packages") {
+        config = config.with_cargo_members(git.root());
+    }
+    if let Some(package) = cli.value_of("package") {
+        config = config.for_package(package)?;
+    }
+    if cli.is_present("json") {
+        config.output.format = Some(Format::Json);
+    }
+    if let Some(format) = cli.value_of("format") {
+        config.output.format = Some(format.parse()?);
+    }
     let cmd = cli.value_of("remote").map(str::to_owned);
     config.output.remote = cmd.or(config.output.remote);
     let cmd = cli.value_of("template").map(str::to_owned);
@@ -58,15 +78,144 @@ fn run(args: Vec<OsString>) -> Result<String> {
 
     debug!("{:#?}", config);
 
+    // Only style Markdown headings, and only on terminals unless asked otherwise
+    match cli.value_of("color") {
+        Some("always") => console::set_colors_enabled(true),
+        Some("never") => console::set_colors_enabled(false),
+        _ => console::set_colors_enabled(console::user_attended()),
+    }
+    if config.output.get_format() != Format::Markdown {
+        console::set_colors_enabled(false);
+    }
+
+    // Work out the next version, if asked
+    if cli.subcommand_matches("next-version").is_some() {
+        let changelog = ChangeLog::try_from_log_with(Vec::new(), &config, &git)?;
+        let range = changelog.range;
+        return changelog
+            .next_version
+            .ok_or_else(|| format_err!("No changes in {} call for a new version", range));
+    }
+
+    // Check a commit message, if asked
+    if let Some(lint) = cli.subcommand_matches("lint") {
+        let message = match lint.value_of("file") {
+            Some(file) => fs::read_to_string(file)
+                .map_err(|e| format_err!("Cannot read file '{}' (Reason: {})", file, e))?,
+            None => {
+                let mut message = String::new();
+                io::stdin().read_to_string(&mut message)?;
+                message
+            }
+        };
+        let problems = changelog::lint_message(&message, &config.conventions);
+        if problems.is_empty() {
+            return Ok(String::new());
+        }
+        let problems: Vec<String> = problems.iter().map(ToString::to_string).collect();
+        return Err(format_err!(
+            "Commit message has tagged lines the change log would drop:\n{}",
+            problems.join("\n")
+        ));
+    }
+
+    // Audit a revision range, if asked
+    if let Some(check) = cli.subcommand_matches("check") {
+        let range = check.values_of_lossy("range").unwrap_or_default();
+        let audit = Audit::from_log_with(range, &config, &git)?;
+        let report = if check.is_present("json") {
+            serde_json::to_string_pretty(&audit)?
+        } else {
+            audit.to_string()
+        };
+        if audit.passed() {
+            return Ok(report);
+        }
+
+        // The report goes to the standard output either way, the failure is only logged
+        println!("{}", report.trim_end());
+        return Err(format_err!(
+            "Check failed with {} policy violations",
+            audit.violations.len()
+        ));
+    }
+
+    // Add the missing releases to an existing change log, if asked
+    if let Some(file) = cli.value_of("update") {
+        let existing = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format_err!("Cannot read file '{}' (Reason: {})", file, e)),
+        };
+        let history = History::from_tags_with(&config, &git)?;
+        let updated = changelog::update(&existing, &history, &config.output)?;
+        fs::write(file, updated)
+            .map_err(|e| format_err!("Cannot write file '{}' (Reason: {})", file, e))?;
+        info!("Updated file '{}'", file);
+        return Ok(String::new());
+    }
+
+    // Update the change log of every package in its own file, if asked
+    if cli.is_present("all-packages") {
+        let workspace = Workspace::from_config_with(&config, &git)?;
+        let mut files = Vec::new();
+        for log in &workspace.packages {
+            files.push(git.root().join(log.package.changelog_file()?));
+        }
+        for (log, file) in workspace.packages.into_iter().zip(files) {
+            let name = log.package.name.clone();
+            let existing = match fs::read_to_string(&file) {
+                Ok(text) => text,
+                Err(ref e) if e.kind() == ErrorKind::NotFound => String::new(),
+                Err(e) => {
+                    let file = file.display();
+                    return Err(format_err!("Cannot read file '{}' (Reason: {})", file, e));
+                }
+            };
+            let updated = log.update_with(&existing, &config, &git)?;
+            if updated == existing {
+                info!("Package '{}' has no new changes", name);
+                continue;
+            }
+            fs::write(&file, updated).map_err(|e| {
+                format_err!("Cannot write file '{}' (Reason: {})", file.display(), e)
+            })?;
+            info!("Updated package '{}' in file '{}'", name, file.display());
+        }
+        return Ok(String::new());
+    }
+
+    // Generate a section for every release, if asked
+    if cli.is_present("history") {
+        let history = History::from_tags_with(&config, &git)?;
+        trace!("{:#?}", history);
+        let report = changelog::render_history(&history, &config.output)?;
+        return write_output(cli.value_of("output"), report);
+    }
+
     // Initialize the revision range
     let range = cli.values_of_lossy("range").unwrap_or_default();
 
     // Generate the change log for the range with the config
-    let changelog = ChangeLog::from_log(range, &config);
+    let changelog = ChangeLog::try_from_log_with(range, &config, &git)?;
     trace!("{:#?}", changelog);
 
     // Render the change log with the given output choices
-    changelog::render(&changelog, &config.output)
+    let report = changelog::render(&changelog, &config.output)?;
+    write_output(cli.value_of("output"), report)
+}
+
+/// Write the report to the given file, if any (otherwise it is shown on the standard output)
+fn write_output(file: Option<&str>, report: String) -> Result<String> {
+    match file {
+        Some(file) => {
+            fs::write(file, report)
+                .map_err(|e| format_err!("Cannot write file '{}' (Reason: {})", file, e))?;
+            info!("Wrote file '{}'", file);
+            Ok(String::new())
+        }
+        None => Ok(report),
+    }
 }
 
 /// The output routine. Just print for now.
@@ -136,6 +285,67 @@ mod tests {
         assert!(super::run(to_args("git-changelog -d v0.1.1..v0.2.1")).is_ok());
         assert!(super::run(to_args("git-changelog -dd v0.1.1..v0.2.1")).is_ok());
         assert!(super::run(to_args("git-changelog -ddd -j")).is_ok());
+        assert!(super::run(to_args("git-changelog -C src -j")).is_ok());
+        assert!(super::run(to_args("git-changelog --history")).is_ok());
+        assert!(super::run(to_args("git-changelog --history -p keepachangelog")).is_ok());
+        assert!(super::run(to_args("git-changelog -C no-such-dir")).is_err());
+        assert!(super::run(to_args("git-changelog no-such-tag..HEAD")).is_err());
+    }
+
+    #[test]
+    fn lint() {
+        use std::env::temp_dir;
+        use std::fs;
+        let file = temp_dir().join("git-changelog-lint.msg");
+        let lint = format!("git-changelog lint --file {}", file.display());
+
+        fs::write(&file, "Subject\n\n- feature: foo\n").unwrap();
+        assert!(super::run(to_args(&lint)).is_ok());
+
+        fs::write(&file, "Subject\n\n- featur: foo\n").unwrap();
+        let problems = super::run(to_args(&lint)).unwrap_err().to_string();
+        assert!(problems.contains("line 3: unknown category 'featur' (did you mean 'feature'?)"));
+
+        fs::remove_file(&file).unwrap();
+        assert!(super::run(to_args(&lint)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "handlebars")]
+    fn output() {
+        use std::env::temp_dir;
+        use std::fs;
+        let file = temp_dir().join("git-changelog-output.yml");
+        let cmd = format!(
+            "git-changelog --format yaml --output {} HEAD^..HEAD",
+            file.display()
+        );
+        assert_eq!(super::run(to_args(&cmd)).unwrap(), "");
+        assert!(fs::read_to_string(&file)
+            .unwrap()
+            .contains("range: HEAD^..HEAD"));
+        fs::remove_file(&file).unwrap();
+
+        let text = super::run(to_args(
+            "git-changelog --format text --color never HEAD^..HEAD",
+        ));
+        assert!(!text.unwrap().starts_with('#'));
+    }
+
+    #[test]
+    #[cfg(feature = "handlebars")]
+    fn update() {
+        use std::env::temp_dir;
+        let file = temp_dir().join("git-changelog-update.json");
+        let cmd = format!("git-changelog --update {} --format json", file.display());
+        assert!(super::run(to_args(&cmd)).is_err());
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn check() {
+        assert!(super::run(to_args("git-changelog check HEAD^..HEAD")).is_ok());
+        assert!(super::run(to_args("git-changelog check -j HEAD^..HEAD")).is_ok());
     }
 
     #[test]
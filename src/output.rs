@@ -1,29 +1,281 @@
 // Copyright 2017-2018 by Aldrin J D'Souza.
 // Licensed under the MIT License <https://opensource.org/licenses/MIT>
 
-use super::{ChangeLog, OutputPreferences, PostProcessor, Result};
+use super::{
+    ChangeLog, Format, History, OutputPreferences, Packaging, PostProcessor, Release, Result,
+};
+#[cfg(feature = "html")]
+use crate::html::render_page;
 #[cfg(feature = "handlebars")]
 use crate::template_hbs::render_template;
+use chrono::{DateTime, FixedOffset, Utc};
+use error::Error;
+use feed::atom;
+use history::UNRELEASED;
 use regex::Regex;
+use serde::Serialize;
 use serde_json::to_string_pretty;
+use serde_yaml::to_string as to_yaml;
+use std::collections::HashMap;
 /// All output concerns.
 use std::fmt;
 
 /// Stub implementation if we're building without handlebars
 #[cfg(not(feature = "handlebars"))]
-fn render_template(_: &str, _: &ChangeLog) -> Result<String> {
-    Err(format_err!(
-        "Built without Handlebars support, must use JSON output"
+fn render_template<T: Serialize>(_: &str, _: &T) -> Result<String> {
+    Err(render_error(
+        "Built without Handlebars support, must use JSON output",
     ))
 }
 
 /// Render the changelog with the given output preferences
 pub fn render(clog: &ChangeLog, out: &OutputPreferences) -> Result<String> {
-    // Depending on the output format, render the log to text
-    let text = if out.json {
-        to_string_pretty(clog).map_err(|e| format_err!("JSON render failed: {}", e))
-    } else {
-        render_template(&out.get_template()?, clog)
+    match out.get_format() {
+        Format::Debian | Format::Rpm => render_package(&[(None, clog)], out),
+        Format::Atom => Ok(atom(&[(&clog.range, clog)])),
+        Format::Html => render_html(&[(&clog.range, clog)], out),
+        _ => render_data(clog, out),
+    }
+}
+
+/// Render the history of all releases with the given output preferences
+pub fn render_history(history: &History, out: &OutputPreferences) -> Result<String> {
+    match out.get_format() {
+        Format::Debian | Format::Rpm => {
+            let releases: Vec<(Option<&str>, &ChangeLog)> = history
+                .releases
+                .iter()
+                .map(|r| (r.tag.as_deref(), &r.changelog))
+                .collect();
+            render_package(&releases, out)
+        }
+        Format::Atom => {
+            // Only tagged releases make it to the feed
+            let releases: Vec<(&str, &ChangeLog)> = history
+                .releases
+                .iter()
+                .filter_map(|r| r.tag.as_deref().map(|tag| (tag, &r.changelog)))
+                .collect();
+            Ok(atom(&releases))
+        }
+        Format::Html => {
+            let releases: Vec<(&str, &ChangeLog)> = history
+                .releases
+                .iter()
+                .map(|r| (r.title.as_str(), &r.changelog))
+                .collect();
+            render_html(&releases, out)
+        }
+        _ => render_data(history, out),
+    }
+}
+
+/// Render the releases as a standalone HTML page (post processing the change text, not the page)
+#[cfg(feature = "html")]
+fn render_html(releases: &[(&str, &ChangeLog)], out: &OutputPreferences) -> Result<String> {
+    let processors = compile_post_processors(&out.post_processors);
+    Ok(render_page(releases, &out.get_stylesheet()?, &processors))
+}
+
+/// Stub implementation if we're building without HTML support
+#[cfg(not(feature = "html"))]
+fn render_html(_: &[(&str, &ChangeLog)], _: &OutputPreferences) -> Result<String> {
+    Err(render_error(
+        "Built without HTML support, must use another format",
+    ))
+}
+
+/// A release in a package change log
+struct PackageEntry<'a> {
+    /// The package version
+    version: String,
+
+    /// The time of the last change
+    time: DateTime<FixedOffset>,
+
+    /// The maintainer (`Name <email>`)
+    maintainer: String,
+
+    /// The change texts
+    changes: Vec<&'a str>,
+}
+
+/// A borrowed subset of the releases in a history (it renders like a `History`)
+#[derive(Serialize)]
+struct Releases<'a> {
+    releases: Vec<&'a Release>,
+
+    /// Tells the templates that the releases go into an existing change log
+    update: bool,
+}
+
+/// Update an existing change log with the releases from the history that it does not have yet.
+///
+/// Releases are found in the existing text with the
+/// [`release_heading`](struct.OutputPreferences.html#structfield.release_heading) pattern. Only the
+/// missing releases are rendered and each one is inserted above the closest older release, leaving
+/// everything else as it is. The "Unreleased" section is regenerated when there are unreleased
+/// changes, and left as it is otherwise.
+/// Link reference definitions in the new releases join the ones already in the text. Only Markdown
+/// change logs can be updated.
+pub fn update(existing: &str, history: &History, out: &OutputPreferences) -> Result<String> {
+    // Only Markdown has the release headings to find
+    let format = out.get_format();
+    if format != Format::Markdown {
+        let why = format!("Cannot update a change log in the {:?} format", format);
+        return Err(Error::InvalidConfig(why).into());
+    }
+
+    // Find the release headings in the existing text
+    let found = release_headings(existing, out)?;
+
+    // The position of each release in the history (newest first)
+    let position: HashMap<&str, usize> = history
+        .releases
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (r.title.as_str(), i))
+        .collect();
+
+    // Group the missing releases by the heading they go above (or the end of the text)
+    let mut missing: Vec<(Option<usize>, Vec<&Release>)> = Vec::new();
+    for (i, release) in history.releases.iter().enumerate() {
+        let present = found.iter().any(|&(_, t)| t == release.title);
+        if present && release.title != UNRELEASED {
+            continue;
+        }
+        let above = found
+            .iter()
+            .position(|&(_, t)| t != UNRELEASED && matches!(position.get(t), Some(&p) if p > i));
+        if let Some(last) = missing.last_mut() {
+            if last.0 == above {
+                last.1.push(release);
+                continue;
+            }
+        }
+        missing.push((above, vec![release]));
+    }
+
+    // Render each group of missing releases in one go, keeping the link definitions apart
+    let definition = Regex::new(LINK_DEFINITION).expect("the link definition pattern is valid");
+    let mut inserts = Vec::new();
+    let mut links = Vec::new();
+    for (above, releases) in missing {
+        let text = render_data(
+            &Releases {
+                releases,
+                update: true,
+            },
+            out,
+        )?;
+        let (defined, text): (Vec<&str>, Vec<&str>) =
+            text.lines().partition(|l| definition.is_match(l));
+        links.extend(defined.into_iter().map(String::from));
+        let text = text.join("\n");
+        if !text.trim().is_empty() {
+            inserts.push((above, format!("{}\n\n", text.trim_end())));
+        }
+    }
+
+    // Stitch the existing sections and the new releases together (an existing "Unreleased" section
+    // gives way only to a new one)
+    let regenerated = history.releases.iter().any(|r| r.title == UNRELEASED);
+    let mut updated = String::from(&existing[..found.first().map_or(existing.len(), |f| f.0)]);
+    for (index, &(start, title)) in found.iter().enumerate() {
+        for (_, text) in inserts.iter().filter(|i| i.0 == Some(index)) {
+            updated.push_str(text);
+        }
+        let end = found.get(index + 1).map_or(existing.len(), |f| f.0);
+        if title != UNRELEASED || !regenerated {
+            updated.push_str(&existing[start..end]);
+        }
+    }
+    for (_, text) in inserts.iter().filter(|i| i.0.is_none()) {
+        if !updated.is_empty() && !updated.ends_with("\n\n") {
+            updated.push_str(if updated.ends_with('\n') {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        updated.push_str(text);
+    }
+
+    // Oldest first, so that each link goes above the ones of the older releases
+    for link in links.iter().rev() {
+        updated = place_link(&updated, link, &definition);
+    }
+
+    Ok(updated)
+}
+
+/// A link reference definition line, with the label
+const LINK_DEFINITION: &str = r"(?m)^\[([^\]]+)\]:\s.*$";
+
+/// Replace the definition of the same label in the text, or put the link above the definitions of
+/// the other releases (or at the end when there are none).
+fn place_link(text: &str, link: &str, definition: &Regex) -> String {
+    let label = definition
+        .captures(link)
+        .and_then(|c| c.get(1))
+        .map_or("", |l| l.as_str());
+    let mut above = None;
+    for found in definition.captures_iter(text) {
+        let (line, other) = match (found.get(0), found.get(1)) {
+            (Some(line), Some(other)) => (line, other.as_str()),
+            _ => continue,
+        };
+        if other == label {
+            return format!("{}{}{}", &text[..line.start()], link, &text[line.end()..]);
+        }
+        if above.is_none() && (label == UNRELEASED || other != UNRELEASED) {
+            above = Some(line.start());
+        }
+    }
+    match above {
+        Some(at) => format!("{}{}\n{}", &text[..at], link, &text[at..]),
+        None => {
+            let text = text.trim_end();
+            if text.is_empty() {
+                format!("{}\n", link)
+            } else {
+                format!("{}\n\n{}\n", text, link)
+            }
+        }
+    }
+}
+
+/// Find the release headings in the existing change log, as the offset and the title of each
+pub fn release_headings<'a>(
+    existing: &'a str,
+    out: &OutputPreferences,
+) -> Result<Vec<(usize, &'a str)>> {
+    let heading = Regex::new(&format!("(?m){}", out.get_release_heading()))
+        .map_err(|e| Error::InvalidConfig(format!("Invalid release heading pattern: {}", e)))?;
+    Ok(heading
+        .captures_iter(existing)
+        .filter_map(|c| {
+            c.get(1)
+                .map(|t| (c.get(0).map_or(0, |m| m.start()), t.as_str()))
+        })
+        .collect())
+}
+
+/// Render the given data with the given output preferences
+fn render_data<T: Serialize>(data: &T, out: &OutputPreferences) -> Result<String> {
+    // Depending on the output format, render the data to text
+    let text = match out.get_format() {
+        Format::Json => {
+            to_string_pretty(data).map_err(|e| render_error(format!("JSON render failed: {}", e)))
+        }
+        Format::Yaml => {
+            to_yaml(data).map_err(|e| render_error(format!("YAML render failed: {}", e)))
+        }
+        Format::Markdown => render_template(&out.get_template()?, data),
+        Format::Text => render_template(&out.get_template()?, data).map(|md| plain_text(&md)),
+        Format::Debian | Format::Rpm => Err(render_error("Package change logs cannot be updated")),
+        Format::Atom => Err(render_error("Feeds cannot be updated")),
+        Format::Html => Err(render_error("HTML pages cannot be updated")),
     };
 
     // Run the post processors on the output
@@ -40,9 +292,177 @@ impl fmt::Display for ChangeLog {
     }
 }
 
+impl fmt::Display for History {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let out = OutputPreferences::default();
+        match render_history(self, &out) {
+            Ok(fine) => write!(f, "{}", fine),
+            Err(err) => write!(f, "Error: {}", err),
+        }
+    }
+}
+
+/// Render the releases (newest first, tagged or not) as a package change log
+fn render_package(
+    releases: &[(Option<&str>, &ChangeLog)],
+    out: &OutputPreferences,
+) -> Result<String> {
+    let packaging = &out.packaging;
+    let mut entries = Vec::new();
+    for &(tag, clog) in releases {
+        entries.push(package_entry(tag, clog, packaging)?);
+    }
+
+    let text = match out.get_format() {
+        Format::Debian => debian(&entries, packaging)?,
+        _ => rpm(&entries),
+    };
+    Ok(post_process(&text, &out.post_processors))
+}
+
+/// Collect the package details of a release
+fn package_entry<'a>(
+    tag: Option<&str>,
+    clog: &'a ChangeLog,
+    packaging: &Packaging,
+) -> Result<PackageEntry<'a>> {
+    // Tags name the version (as the tag pattern reads it), the rest must be configured
+    let version = match tag {
+        Some(tag) => clog
+            .version
+            .clone()
+            .unwrap_or_else(|| tag.trim_start_matches('v').to_owned()),
+        None => packaging.version.clone().ok_or_else(|| {
+            Error::InvalidConfig(String::from(
+                "Untagged changes need a `packaging.version` for the package format",
+            ))
+        })?,
+    };
+    let version = match packaging.revision {
+        Some(ref revision) => format!("{}-{}", version, revision),
+        None => version,
+    };
+
+    // The last commit has the time (and the default maintainer)
+    let last = clog
+        .commits
+        .iter()
+        .filter_map(|c| DateTime::parse_from_rfc2822(&c.time).ok().map(|t| (t, c)))
+        .max_by_key(|&(t, _)| t);
+    let time = last.map_or_else(|| Utc::now().into(), |(t, _)| t);
+    let maintainer = match (packaging.maintainer.as_ref(), last) {
+        (Some(maintainer), _) => maintainer.to_owned(),
+        (None, Some((_, commit))) => format!("{} <{}>", commit.author, commit.email),
+        (None, None) => {
+            return Err(Error::InvalidConfig(String::from(
+                "The package format needs a `packaging.maintainer`",
+            ))
+            .into())
+        }
+    };
+
+    let changes = clog
+        .scopes
+        .iter()
+        .flat_map(|s| s.categories.iter())
+        .flat_map(|c| c.changes.iter())
+        .map(|c| c.text.trim())
+        .collect();
+
+    Ok(PackageEntry {
+        version,
+        time,
+        maintainer,
+        changes,
+    })
+}
+
+/// Render the entries in the Debian change log syntax
+fn debian(entries: &[PackageEntry], packaging: &Packaging) -> Result<String> {
+    let name = packaging.name.as_ref().ok_or_else(|| {
+        Error::InvalidConfig(String::from("The Debian format needs a `packaging.name`"))
+    })?;
+    let distribution = packaging.distribution.as_deref().unwrap_or("unstable");
+    let urgency = packaging.urgency.as_deref().unwrap_or("medium");
+
+    let mut text = String::new();
+    for entry in entries {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&format!(
+            "{} ({}) {}; urgency={}\n\n",
+            name, entry.version, distribution, urgency
+        ));
+        for line in bullets(&entry.changes, "  * ", "    ") {
+            text.push_str(&line);
+            text.push('\n');
+        }
+        let time = entry.time.format("%a, %d %b %Y %H:%M:%S %z");
+        text.push_str(&format!("\n -- {}  {}\n", entry.maintainer, time));
+    }
+    Ok(text)
+}
+
+/// Render the entries in the RPM `%changelog` syntax
+fn rpm(entries: &[PackageEntry]) -> String {
+    let mut text = String::from("%changelog\n");
+    for (n, entry) in entries.iter().enumerate() {
+        if n > 0 {
+            text.push('\n');
+        }
+        // RPM versions always have a release
+        let version = if entry.version.contains('-') {
+            entry.version.clone()
+        } else {
+            format!("{}-1", entry.version)
+        };
+        let time = entry.time.format("%a %b %d %Y");
+        text.push_str(&format!("* {} {} - {}\n", time, entry.maintainer, version));
+        for line in bullets(&entry.changes, "- ", "  ") {
+            text.push_str(&line);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Turn the changes into bullet lines (with indented continuations), or a placeholder if there are none
+fn bullets(changes: &[&str], bullet: &str, indent: &str) -> Vec<String> {
+    if changes.is_empty() {
+        return vec![format!("{}No notable changes.", bullet)];
+    }
+    let mut lines = Vec::new();
+    for change in changes {
+        let mut parts = change.lines().map(str::trim).filter(|l| !l.is_empty());
+        if let Some(first) = parts.next() {
+            lines.push(format!("{}{}", bullet, first));
+        }
+        lines.extend(parts.map(|l| format!("{}{}", indent, l)));
+    }
+    lines
+}
+
+/// Drop the Markdown heading markers and link targets from the rendered text
+fn plain_text(markdown: &str) -> String {
+    let heading = Regex::new(r"(?m)^#+\s+").expect("valid heading pattern");
+    let link = Regex::new(r"\[([^\]]*)\]\([^)]*\)").expect("valid link pattern");
+    let text = heading.replace_all(markdown, "");
+    link.replace_all(&text, "$1").into_owned()
+}
+
+/// A render error in the crate result
+fn render_error<T: Into<String>>(why: T) -> ::anyhow::Error {
+    Error::RenderError(why.into()).into()
+}
+
 /// Post process the output before returning it
 fn post_process(output: &str, post_processors: &[PostProcessor]) -> String {
-    // Compile the post processor regular expressions
+    run_post_processors(output, &compile_post_processors(post_processors))
+}
+
+/// Compile the post processor regular expressions (with their replacements), skipping invalid ones
+pub(crate) fn compile_post_processors(post_processors: &[PostProcessor]) -> Vec<(Regex, &str)> {
     let mut processors = Vec::new();
     for processor in post_processors {
         // Processor the lookup regular expression
@@ -57,7 +477,11 @@ fn post_process(output: &str, post_processors: &[PostProcessor]) -> String {
             warn!("Post-processor {:#?} is invalid", processor);
         }
     }
+    processors
+}
 
+/// Run the compiled post processors on every line of the output
+pub(crate) fn run_post_processors(output: &str, processors: &[(Regex, &str)]) -> String {
     // Track the processed output
     let mut processed = Vec::new();
 
@@ -67,7 +491,7 @@ fn post_process(output: &str, post_processors: &[PostProcessor]) -> String {
         let mut next: String = line.to_string();
 
         // Run all available processors through it
-        for processor in &processors {
+        for processor in processors {
             // Replace the pattern as appropriate
             next = processor.0.replace_all(&next, processor.1).to_string();
         }
@@ -80,10 +504,277 @@ fn post_process(output: &str, post_processors: &[PostProcessor]) -> String {
     processed.join("\n")
 }
 
+/// Escape the XML and HTML special characters, dropping the control characters XML 1.0 forbids
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c if c.is_control() && c != '\n' && c != '\t' && c != '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 #[cfg(test)]
 mod tests {
     use super::PostProcessor;
 
+    /// A history with a tagged release and untagged changes
+    fn package_history() -> super::History {
+        use changelog::ChangeLog;
+        use history::{History, Release};
+        use input::Configuration;
+
+        let config = Configuration::from_yaml(include_str!("assets/changelog.yml")).unwrap();
+        let release = |tag: Option<&str>, time: &str, body: &str| {
+            let lines = vec!["abc", "Jane Doe", time, "Subject", body];
+            let mut commit =
+                ::commit::Commit::from_lines(lines.into_iter().map(String::from).collect());
+            commit.email = String::from("jane@example.com");
+            Release {
+                tag: tag.map(String::from),
+                title: String::from(tag.unwrap_or("Unreleased")),
+                changelog: ChangeLog::from(vec![commit].into_iter(), &config),
+            }
+        };
+        History {
+            releases: vec![
+                release(
+                    None,
+                    "Mon, 23 Oct 2017 09:05:00 +0200",
+                    "- fix: Handle empty\nresponses",
+                ),
+                release(
+                    Some("v0.2.0"),
+                    "Sun, 22 Oct 2017 17:26:56 -0400",
+                    "- feature: Filters",
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn debian() {
+        use super::{render_history, Format, OutputPreferences};
+        use regex::Regex;
+
+        let mut out = OutputPreferences {
+            format: Some(Format::Debian),
+            ..OutputPreferences::default()
+        };
+        let history = package_history();
+
+        // The package name and the version of the untagged changes are required
+        assert!(render_history(&history, &out).is_err());
+        out.packaging.name = Some(String::from("git-changelog"));
+        assert!(render_history(&history, &out).is_err());
+        out.packaging.version = Some(String::from("0.3.0~rc1"));
+        out.packaging.revision = Some(String::from("1"));
+        let text = render_history(&history, &out).unwrap();
+
+        // Check the syntax (see `man deb-changelog`) line by line
+        let header = Regex::new(
+            r"^[a-z0-9][a-z0-9+.-]+ \([0-9][A-Za-z0-9.+~:-]*\) [a-z-]+; urgency=[a-z]+$",
+        )
+        .unwrap();
+        let change = Regex::new(r"^  (\* |  )\S").unwrap();
+        let trailer = Regex::new(r"^ -- [^<]+ <[^>]+>  [A-Z][a-z]{2}, \d{2} [A-Z][a-z]{2} \d{4} \d{2}:\d{2}:\d{2} [+-]\d{4}$").unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        let mut rest = &lines[..];
+        while !rest.is_empty() {
+            // A header, a blank line, the changes, a blank line and the trailer
+            assert!(header.is_match(rest[0]), "{}", rest[0]);
+            assert_eq!(rest[1], "");
+            let end = rest
+                .iter()
+                .position(|l| l.starts_with(" -- "))
+                .expect("a trailer");
+            assert!(trailer.is_match(rest[end]), "{}", rest[end]);
+            assert_eq!(rest[end - 1], "");
+            assert!(end > 3);
+            for line in &rest[2..end - 1] {
+                assert!(change.is_match(line), "{}", line);
+            }
+
+            // Entries are separated by a blank line
+            rest = &rest[end + 1..];
+            if !rest.is_empty() {
+                assert_eq!(rest[0], "");
+                rest = &rest[1..];
+            }
+        }
+        assert_eq!(
+            lines[0],
+            "git-changelog (0.3.0~rc1-1) unstable; urgency=medium"
+        );
+        assert_eq!(lines[2], "  * Handle empty");
+        assert_eq!(lines[3], "    responses");
+        assert_eq!(
+            lines[5],
+            " -- Jane Doe <jane@example.com>  Mon, 23 Oct 2017 09:05:00 +0200"
+        );
+        assert_eq!(lines[7], "git-changelog (0.2.0-1) unstable; urgency=medium");
+    }
+
+    #[test]
+    fn rpm() {
+        use super::{render_history, Format, OutputPreferences};
+        let mut out = OutputPreferences {
+            format: Some(Format::Rpm),
+            ..OutputPreferences::default()
+        };
+        out.packaging.version = Some(String::from("0.3.0"));
+        out.packaging.maintainer = Some(String::from("Joe <joe@example.com>"));
+        let text = render_history(&package_history(), &out).unwrap();
+        let expected = "%changelog\n\
+                        * Mon Oct 23 2017 Joe <joe@example.com> - 0.3.0-1\n\
+                        - Handle empty\n  responses\n\n\
+                        * Sun Oct 22 2017 Joe <joe@example.com> - 0.2.0-1\n\
+                        - Filters";
+        assert_eq!(text, expected);
+
+        // The version of a tag is the one the tag pattern reads
+        let mut history = package_history();
+        history.releases[1].tag = Some(String::from("cli-v0.2.0"));
+        history.releases[1].changelog.version = Some(String::from("0.2.0"));
+        assert_eq!(render_history(&history, &out).unwrap(), expected);
+    }
+
+    #[test]
+    fn plain_text() {
+        use super::plain_text;
+        let markdown =
+            "# v1.0 (2018-01-01)\n- [Add X](https://x/pull/1)\n## API\n- Keep #1 [as is]";
+        let text = "v1.0 (2018-01-01)\n- Add X\nAPI\n- Keep #1 [as is]";
+        assert_eq!(plain_text(markdown), text);
+    }
+
+    #[test]
+    #[cfg(feature = "handlebars")]
+    fn update() {
+        use super::{Format, History, OutputPreferences, Release};
+        use changelog::ChangeLog;
+        use commit::Commit;
+
+        // A release with a single commit
+        let release = |title: &str| {
+            let commit = Commit {
+                summary: format!("Release {}", title),
+                ..Commit::default()
+            };
+            let changelog = ChangeLog {
+                commits: vec![commit],
+                ..ChangeLog::default()
+            };
+            Release {
+                tag: Some(title.to_string()),
+                title: title.to_string(),
+                changelog,
+            }
+        };
+
+        let history = History {
+            releases: vec![release("v3"), release("v2"), release("v1")],
+        };
+        let out = OutputPreferences::default();
+
+        // The missing releases are inserted around the existing one, which is left alone
+        let existing = "Intro\n\n# v2 (edited)\n\n- Edited\n";
+        let updated = super::update(existing, &history, &out).unwrap();
+        let v3 = updated.find("# v3").unwrap();
+        let v2 = updated.find("# v2 (edited)\n\n- Edited\n").unwrap();
+        let v1 = updated.find("# v1").unwrap();
+        assert!(updated.starts_with("Intro\n\n"));
+        assert!(v3 < v2 && v2 < v1);
+
+        // Nothing changes when all releases are present
+        assert_eq!(super::update(&updated, &history, &out).unwrap(), updated);
+
+        // Bad patterns are reported
+        let bad = OutputPreferences {
+            release_heading: Some(String::from("(unclosed")),
+            ..OutputPreferences::default()
+        };
+        assert!(super::update(existing, &history, &bad).is_err());
+
+        // Only Markdown change logs are updated
+        let json = OutputPreferences {
+            format: Some(Format::Json),
+            ..OutputPreferences::default()
+        };
+        assert!(super::update(existing, &history, &json).is_err());
+
+        // The "Unreleased" section stays unless there are unreleased changes to replace it
+        let existing = "# Unreleased\n\n- Pending\n\n# v3\n\n# v2\n\n# v1\n";
+        let updated = super::update(existing, &history, &out).unwrap();
+        assert_eq!(updated, existing);
+        let mut unreleased = release("Unreleased");
+        unreleased.tag = None;
+        let mut history = history;
+        history.releases.insert(0, unreleased);
+        let updated = super::update(existing, &history, &out).unwrap();
+        assert!(!updated.contains("- Pending"));
+        assert!(updated.starts_with("# Unreleased"));
+    }
+
+    #[test]
+    #[cfg(feature = "handlebars")]
+    fn update_keepachangelog() {
+        use super::{History, Release};
+        use changelog::ChangeLog;
+        use commit::Commit;
+        use input::Configuration;
+
+        // A release with a single fix
+        let release = |title: &str| {
+            let commit = Commit {
+                summary: format!("Release {}", title),
+                ..Commit::default()
+            };
+            let changelog = ChangeLog {
+                commits: vec![commit],
+                date: String::from("2018-01-01"),
+                compare_url: Some(format!("https://x/compare/{}", title)),
+                ..ChangeLog::default()
+            };
+            Release {
+                tag: Some(title.to_string()),
+                title: title.to_string(),
+                changelog,
+            }
+        };
+
+        let out = Configuration::from_yaml("preset: keepachangelog")
+            .unwrap()
+            .output;
+        let old = History {
+            releases: vec![release("v2"), release("v1")],
+        };
+        let existing = super::render_history(&old, &out).unwrap();
+        let history = History {
+            releases: vec![release("v4"), release("v3"), release("v2"), release("v1")],
+        };
+
+        // The preamble is not repeated and the new links join the others at the end
+        let updated = super::update(&existing, &history, &out).unwrap();
+        assert_eq!(updated.matches("# Changelog").count(), 1);
+        let links = "[v4]: https://x/compare/v4\n\
+                     [v3]: https://x/compare/v3\n\
+                     [v2]: https://x/compare/v2\n\
+                     [v1]: https://x/compare/v1";
+        assert!(updated.trim_end().ends_with(links));
+        let v3 = updated.find("## [v3]").unwrap();
+        let v2 = updated.find("## [v2]").unwrap();
+        assert!(updated.find("## [v4]").unwrap() < v3 && v3 < v2);
+        assert_eq!(super::update(&updated, &history, &out).unwrap(), updated);
+    }
+
     #[test]
     fn post_process() {
         let input = String::from("Fixed JIRA-1234\nfoo");
@@ -1,17 +1,24 @@
 // Licensed under the MIT License <https://opensource.org/licenses/MIT>
-use super::{ChangeLog, Result};
+use super::Result;
+use error::Error;
 use handlebars::{Context, Handlebars, Helper, Output, RenderContext, RenderError};
+use serde::Serialize;
 
 type RenderResult = ::std::result::Result<(), RenderError>;
 
-pub fn render_template(template: &str, clog: &ChangeLog) -> Result<String> {
+pub fn render_template<T: Serialize>(template: &str, data: &T) -> Result<String> {
     let mut hbs = Handlebars::new();
     hbs.register_helper("tidy-change", Box::new(tidy));
-    hbs.render_template(template, clog)
-        .map_err(|e| format_err!("Handlebar render failed: {}", e))
+    hbs.register_template_string("changelog", template)
+        .map_err(|e| Error::TemplateError(format!("Handlebar template is invalid: {}", e)))?;
+    hbs.render("changelog", data)
+        .map_err(|e| Error::RenderError(format!("Handlebar render failed: {}", e)).into())
 }
 
 /// A handlebar helper to tidy up markdown lists used to render changes.
+///
+/// The change can be given as a [`Change`](../struct.Change.html) or as its text, so older templates that
+/// pass `this` while looping over changes keep working.
 fn tidy(
     h: &Helper,
     _: &Handlebars,
@@ -20,7 +27,9 @@ fn tidy(
     out: &mut dyn Output,
 ) -> RenderResult {
     if let Some(indent) = h.param(0).and_then(|v| v.value().as_str()) {
-        if let Some(text) = h.param(1).and_then(|v| v.value().as_str()) {
+        let change = h.param(1).map(|v| v.value());
+        let text = change.and_then(|v| v.as_str().or_else(|| v["text"].as_str()));
+        if let Some(text) = text {
             let mut lines = text.lines();
             if let Some(first) = lines.next() {
                 out.write(first.trim())?;
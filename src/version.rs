@@ -0,0 +1,258 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+// Semantic version bumps
+use changelog::ChangeLog;
+use error::Error;
+use git::GitBackend;
+use input::{Bump, Conventions, Keyword};
+use regex::Regex;
+use semver::{Prerelease, Version};
+
+/// The version of the first release (when there is no version tag yet)
+const FIRST_VERSION: Version = Version::new(0, 1, 0);
+
+/// Parse the version in a tag (with an optional `v` prefix)
+pub fn parse_version(tag: &str) -> Option<Version> {
+    let tag = tag.trim();
+    let version = tag
+        .strip_prefix('v')
+        .or_else(|| tag.strip_prefix('V'))
+        .unwrap_or(tag);
+    Version::parse(version).ok()
+}
+
+/// The release tags, as picked by the conventions.
+pub struct ReleaseTags {
+    /// The pattern that picks the release tags (and their versions)
+    pattern: Option<Regex>,
+
+    /// Leave out pre-releases?
+    skip_prereleases: bool,
+}
+
+impl ReleaseTags {
+    /// Construct from the conventions, failing if the tag pattern is invalid
+    pub fn new(conventions: &Conventions) -> Result<Self, Error> {
+        let pattern = match conventions.tag_pattern {
+            Some(ref p) => Some(Regex::new(p).map_err(|why| {
+                Error::InvalidConfig(format!("Invalid tag pattern '{}' (Reason: {})", p, why))
+            })?),
+            None => None,
+        };
+        Ok(ReleaseTags {
+            pattern,
+            skip_prereleases: conventions.skip_prereleases,
+        })
+    }
+
+    /// Get the version of the tag (if it has one)
+    pub fn version(&self, tag: &str) -> Option<Version> {
+        match self.pattern {
+            Some(ref pattern) => pattern
+                .captures(tag)
+                .and_then(|c| c.get(1))
+                .and_then(|v| parse_version(v.as_str())),
+            None => parse_version(tag),
+        }
+    }
+
+    /// Pick the release tags (the ones with versions) from the given ones, ordered by their versions
+    /// (oldest first)
+    pub fn sort(&self, tags: Vec<String>) -> Vec<(String, Version)> {
+        let mut releases: Vec<(String, Version)> = tags
+            .into_iter()
+            .filter_map(|tag| match (self.version(&tag), &self.pattern) {
+                (Some(ref v), _) if self.skip_prereleases && !v.pre.is_empty() => None,
+                (Some(v), _) => Some((tag, v)),
+                (None, Some(pattern)) if pattern.is_match(&tag) => {
+                    warn!("Tag '{}' is ignored (Reason: no semantic version)", tag);
+                    None
+                }
+                (None, _) => {
+                    debug!("Tag '{}' is ignored (Reason: not a release)", tag);
+                    None
+                }
+            })
+            .collect();
+        releases.sort_by(|a, b| a.1.cmp(&b.1));
+        releases
+    }
+
+    /// Get all the release tags reachable from `HEAD`, ordered by their versions (oldest first)
+    pub fn all(&self, backend: &dyn GitBackend) -> Vec<String> {
+        match backend.reachable_tags() {
+            Ok(tags) => self.sort(tags).into_iter().map(|(tag, _)| tag).collect(),
+            Err(why) => {
+                error!("Cannot list tags (Reason: {})", why);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Get the release tag with the highest version that is reachable from `HEAD`
+    pub fn last(&self, backend: &dyn GitBackend) -> Option<String> {
+        match backend.reachable_tags() {
+            Ok(tags) => self.sort(tags).pop().map(|(tag, _)| tag),
+            Err(why) => {
+                error!("Cannot list tags (Reason: {})", why);
+                None
+            }
+        }
+    }
+}
+
+/// Get the largest bump the changes in the change log call for
+pub fn bump_for(clog: &ChangeLog, conventions: &Conventions) -> Option<Bump> {
+    let bump = |keywords: &[Keyword], tag: &str| {
+        keywords.iter().find(|k| k.tag == tag).and_then(|k| k.bump)
+    };
+    clog.scopes
+        .iter()
+        .flat_map(|s| s.categories.iter())
+        .flat_map(|c| c.changes.iter())
+        .flat_map(|c| {
+            vec![
+                bump(&conventions.scopes, &c.scope_tag),
+                bump(&conventions.categories, &c.category_tag),
+            ]
+        })
+        .flatten()
+        .max()
+}
+
+/// Get the version that follows the given one (none is before the first release) with the given bump.
+///
+/// Before 1.0, breaking changes bump the minor version (`0.2.3` goes to `0.3.0`) and with a `0.0.z`
+/// version every change bumps the patch version (`0.0.3` goes to `0.0.4`). A pre-release version is released
+/// without a bump when it already has one that is large enough (`1.0.0-rc.1` goes to `1.0.0`).
+pub fn next_version(current: Option<&Version>, bump: Bump) -> Version {
+    let current = match current {
+        Some(current) => current,
+        None => return FIRST_VERSION,
+    };
+
+    // Scale down the bump of unstable versions
+    let bump = match (current.major, current.minor, bump) {
+        (0, 0, _) => Bump::Patch,
+        (0, _, Bump::Major) => Bump::Minor,
+        (_, _, bump) => bump,
+    };
+
+    // Pre-releases need no bump if they already have the one asked for
+    let mut next = Version::new(current.major, current.minor, current.patch);
+    if !current.pre.is_empty() {
+        let released = match bump {
+            Bump::Major => current.minor == 0 && current.patch == 0,
+            Bump::Minor => current.patch == 0,
+            Bump::Patch => true,
+        };
+        if released {
+            return next;
+        }
+    }
+
+    match bump {
+        Bump::Major => {
+            next.major += 1;
+            next.minor = 0;
+            next.patch = 0;
+        }
+        Bump::Minor => {
+            next.minor += 1;
+            next.patch = 0;
+        }
+        Bump::Patch => next.patch += 1,
+    }
+    next.pre = Prerelease::EMPTY;
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bump_for, next_version, parse_version, ReleaseTags};
+    use changelog::ChangeLog;
+    use commit::Commit;
+    use error::Error;
+    use input::{Bump, Configuration, Conventions};
+
+    #[test]
+    fn release_tags() {
+        let sorted = |conventions: &Conventions, tags: &[&str]| -> Vec<String> {
+            let tags = tags.iter().map(|t| t.to_string()).collect();
+            let releases = ReleaseTags::new(conventions).unwrap().sort(tags);
+            releases.into_iter().map(|(tag, _)| tag).collect()
+        };
+        let tags = ["v0.10.0", "release-x", "v0.9.0", "v1.0.0", "v1.0.0-rc.1"];
+
+        // Versions sort as versions, and the tags that are not versions are not releases
+        let mut conventions = Conventions::default();
+        let all = ["v0.9.0", "v0.10.0", "v1.0.0-rc.1", "v1.0.0"];
+        assert_eq!(sorted(&conventions, &tags), all);
+        conventions.skip_prereleases = true;
+        assert_eq!(sorted(&conventions, &tags)[2], "v1.0.0");
+
+        // A pattern picks one family of tags
+        conventions.tag_pattern = Some(String::from("^cli-v(.+)$"));
+        let tags = [
+            "cli-v1.10.0",
+            "server-v3.0.0",
+            "cli-v1.2.0",
+            "cli-vX",
+            "cli-v2.0.0-beta",
+        ];
+        assert_eq!(sorted(&conventions, &tags), ["cli-v1.2.0", "cli-v1.10.0"]);
+        let version = ReleaseTags::new(&conventions)
+            .unwrap()
+            .version("cli-v1.2.0");
+        assert_eq!(version, parse_version("1.2.0"));
+
+        // A bad pattern is a configuration error
+        conventions.tag_pattern = Some(String::from("^cli-v(.+$"));
+        match ReleaseTags::new(&conventions) {
+            Err(Error::InvalidConfig(_)) => {}
+            _ => panic!("The bad tag pattern is accepted"),
+        }
+    }
+
+    #[test]
+    fn versions() {
+        let next =
+            |current: &str, bump| next_version(parse_version(current).as_ref(), bump).to_string();
+        assert_eq!(next("v1.2.3", Bump::Major), "2.0.0");
+        assert_eq!(next("v1.2.3", Bump::Minor), "1.3.0");
+        assert_eq!(next("1.2.3", Bump::Patch), "1.2.4");
+        assert_eq!(next("v0.2.3", Bump::Major), "0.3.0");
+        assert_eq!(next("v0.2.3", Bump::Minor), "0.3.0");
+        assert_eq!(next("v0.2.3", Bump::Patch), "0.2.4");
+        assert_eq!(next("v0.0.3", Bump::Major), "0.0.4");
+        assert_eq!(next("v2.0.0-rc.1", Bump::Major), "2.0.0");
+        assert_eq!(next("v2.1.0-rc.1", Bump::Major), "3.0.0");
+        assert_eq!(next("v2.1.0-rc.1", Bump::Minor), "2.1.0");
+        assert_eq!(next("v2.1.1-rc.1", Bump::Patch), "2.1.1");
+        assert_eq!(next("not-a-version", Bump::Major), "0.1.0");
+    }
+
+    #[test]
+    fn bumps() {
+        let config = Configuration::from_yaml(include_str!("assets/changelog.yml")).unwrap();
+        let bump = |body: &str| {
+            let lines = vec![
+                "abc",
+                "Jane",
+                "Sun, 22 Oct 2017 17:26:56 -0400",
+                "Subject",
+                body,
+            ];
+            let commit = Commit::from_lines(lines.into_iter().map(String::from).collect());
+            bump_for(
+                &ChangeLog::from(vec![commit].into_iter(), &config),
+                &config.conventions,
+            )
+        };
+        assert_eq!(bump("- note: Nothing"), None);
+        assert_eq!(bump("- fix: Bug"), Some(Bump::Patch));
+        assert_eq!(bump("- fix: Bug\n- feature: New"), Some(Bump::Minor));
+        assert_eq!(bump("- feature: New\n- break: Old"), Some(Bump::Major));
+    }
+}
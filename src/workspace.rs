@@ -0,0 +1,518 @@
+// Copyright 2017-2018 by Aldrin J D'Souza.
+// Licensed under the MIT License <https://opensource.org/licenses/MIT>
+
+// The change logs of all the packages in a repository
+use changelog::{identity_map, ChangeLog};
+use commit::{Commit, CommitList};
+use error::Error;
+use git::{GitBackend, GitCommand, FIELD_SEPARATOR, RECORD_SEPARATOR};
+use glob::{glob, Pattern};
+use history::{History, Release, UNRELEASED};
+use input::{Configuration, Package};
+use output::{release_headings, update};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use toml::Value;
+use version::ReleaseTags;
+
+/// A changelog for every package in the repository.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Workspace {
+    /// The package change logs, in the order the packages are configured.
+    pub packages: Vec<PackageLog>,
+}
+
+/// The changes to a single package.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct PackageLog {
+    /// The package
+    pub package: Package,
+
+    /// The changes since the last release of the package.
+    pub changelog: ChangeLog,
+}
+
+impl Workspace {
+    /// Generate the change logs of the configured packages
+    pub fn from_config(config: &Configuration) -> Result<Self, Error> {
+        Self::from_config_with(config, &GitCommand::default())
+    }
+
+    /// Generate the change logs of the configured packages, reading the log with the given backend.
+    ///
+    /// The log is read once for all packages, and a commit that changes the files of several packages is
+    /// in the change log of each. It is an error to have no packages configured.
+    pub fn from_config_with(
+        config: &Configuration,
+        backend: &dyn GitBackend,
+    ) -> Result<Self, Error> {
+        config.conventions.validate()?;
+        if config.packages.is_empty() {
+            return Err(Error::InvalidConfig(String::from(
+                "No packages are configured",
+            )));
+        }
+
+        // The changes to each package start at its last release
+        let configs: Vec<Configuration> = config
+            .packages
+            .iter()
+            .map(|p| config.clone().with_package(p.clone()))
+            .collect();
+        let mut starts: Vec<Option<String>> = Vec::new();
+        for config in &configs {
+            starts.push(ReleaseTags::new(&config.conventions)?.last(backend));
+        }
+
+        // The commits after the common ancestor of the releases cover them all
+        let base = match starts.iter().cloned().collect::<Option<Vec<String>>>() {
+            Some(ref tags) if !tags.is_empty() => backend.merge_base(tags).unwrap_or_else(|why| {
+                debug!("No common ancestor of {} (Reason: {})", tags.join(" "), why);
+                None
+            }),
+            _ => None,
+        };
+        let range = base
+            .as_ref()
+            .map_or_else(|| String::from("HEAD"), |b| format!("{}..HEAD", b));
+        let args = vec![range];
+        info!("Using revision range '{}' for all packages", args[0]);
+        let changes = backend.changed_files(&args).map_err(Error::from_git)?;
+        let patterns = config.conventions.reference_patterns();
+        let commits: Vec<Commit> = CommitList::try_from_backend(args, backend)?
+            .with_references(&patterns)?
+            .collect();
+
+        // The parents and the files of each commit
+        let parents: HashMap<&str, &[String]> = changes
+            .iter()
+            .map(|c| (c.sha.as_str(), c.parents.as_slice()))
+            .collect();
+        let files: HashMap<&str, &[String]> = changes
+            .iter()
+            .map(|c| (c.sha.as_str(), c.files.as_slice()))
+            .collect();
+
+        // The people who contributed before the commits (all the contacts are mapped in one go)
+        let earlier = match base {
+            Some(base) => backend.contacts_in_log(&[base]).unwrap_or_else(|why| {
+                warn!("Cannot find the earlier contributors (Reason: {})", why);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        let contacts = commits.iter().flat_map(Commit::contacts);
+        let ids = identity_map(earlier.iter().cloned().chain(contacts).collect(), backend);
+        let id = |contact: &String| ids.get(contact).cloned().unwrap_or_default();
+        let before: HashSet<String> = earlier.iter().map(id).collect();
+
+        let mut packages = Vec::new();
+        for (config, start) in configs.iter().zip(starts) {
+            let package = config.package.clone().unwrap_or_default();
+
+            // Leave out the commits the last release already has
+            let released = match start {
+                Some(ref tag) => ancestors(&revision(tag, backend)?, &parents),
+                None => HashSet::new(),
+            };
+            let picked = commits.iter().filter(|c| {
+                let sha = c.sha.as_str();
+                !released.contains(sha)
+                    && matches!(files.get(sha), Some(f) if f.iter().any(|f| package.matches(f)))
+            });
+
+            // The contributors to the release are not new either
+            let mut known = before.clone();
+            known.extend(
+                commits
+                    .iter()
+                    .filter(|c| released.contains(c.sha.as_str()))
+                    .flat_map(Commit::contacts)
+                    .map(|c| id(&c)),
+            );
+
+            let range = start.map_or_else(|| String::from("HEAD"), |t| format!("{}..HEAD", t));
+            let changelog =
+                ChangeLog::from_log_commits(picked.cloned(), vec![range], &known, config, backend);
+            packages.push(PackageLog { package, changelog });
+        }
+        Ok(Workspace { packages })
+    }
+}
+
+impl PackageLog {
+    /// Merge the changes into the existing change log of the package (see `changelog::update`).
+    ///
+    /// The changes are the "Unreleased" section. The package releases the existing text does not have
+    /// yet are added too, so an empty text gets the whole package history. The log is read once for
+    /// all of them.
+    pub fn update_with(
+        self,
+        existing: &str,
+        config: &Configuration,
+        backend: &dyn GitBackend,
+    ) -> ::Result<String> {
+        let package = self.package.clone();
+        let config = config.clone().with_package(self.package);
+        let present: Vec<&str> = release_headings(existing, &config.output)?
+            .into_iter()
+            .map(|h| h.1)
+            .collect();
+        let tags = ReleaseTags::new(&config.conventions)?.all(backend);
+
+        // The commits up to the last missing release cover all the missing ones
+        let last = tags.iter().rposition(|t| !present.contains(&t.as_str()));
+        let (changes, commits) = match last {
+            Some(last) => {
+                let args = vec![tags[last].clone()];
+                let changes = backend.changed_files(&args).map_err(Error::from_git)?;
+                let patterns = config.conventions.reference_patterns();
+                let commits: Vec<Commit> = CommitList::try_from_backend(args, backend)?
+                    .with_references(&patterns)?
+                    .collect();
+                (changes, commits)
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+        let parents: HashMap<&str, &[String]> = changes
+            .iter()
+            .map(|c| (c.sha.as_str(), c.parents.as_slice()))
+            .collect();
+        let files: HashMap<&str, &[String]> = changes
+            .iter()
+            .map(|c| (c.sha.as_str(), c.files.as_slice()))
+            .collect();
+        let contacts = commits.iter().flat_map(Commit::contacts).collect();
+        let ids = identity_map(contacts, backend);
+        let id = |contact: &String| ids.get(contact).cloned().unwrap_or_default();
+
+        // Each missing release has the package commits since the release before it
+        let mut releases = Vec::new();
+        let mut previous: Option<&str> = None;
+        let mut released = HashSet::new();
+        for (index, tag) in tags.iter().enumerate() {
+            let mut changelog = ChangeLog::default();
+            if matches!(last, Some(last) if index <= last) {
+                let reachable = ancestors(&revision(tag, backend)?, &parents);
+                if !present.contains(&tag.as_str()) {
+                    let picked = commits.iter().filter(|c| {
+                        let sha = c.sha.as_str();
+                        reachable.contains(sha)
+                            && !released.contains(sha)
+                            && files
+                                .get(sha)
+                                .into_iter()
+                                .flat_map(|f| f.iter())
+                                .any(|f| package.matches(f))
+                    });
+                    let known: HashSet<String> = commits
+                        .iter()
+                        .filter(|c| released.contains(c.sha.as_str()))
+                        .flat_map(Commit::contacts)
+                        .map(|c| id(&c))
+                        .collect();
+                    let range = previous.map_or_else(|| tag.clone(), |p| format!("{}..{}", p, tag));
+                    changelog = ChangeLog::from_log_commits(
+                        picked.cloned(),
+                        vec![range],
+                        &known,
+                        &config,
+                        backend,
+                    );
+                }
+                released = reachable;
+            }
+            releases.push(Release {
+                tag: Some(tag.clone()),
+                title: tag.clone(),
+                changelog,
+            });
+            previous = Some(tag);
+        }
+        if !self.changelog.commits.is_empty() {
+            releases.push(Release {
+                tag: None,
+                title: String::from(UNRELEASED),
+                changelog: self.changelog,
+            });
+        }
+
+        // Newest first
+        releases.reverse();
+        update(existing, &History { releases }, &config.output)
+    }
+}
+
+/// Find the commit SHA of the revision
+fn revision(revision: &str, backend: &dyn GitBackend) -> Result<String, Error> {
+    let record = backend
+        .get_commit_message(revision)
+        .map_err(Error::from_git)?;
+    let sha = record
+        .trim_start_matches(RECORD_SEPARATOR)
+        .split(FIELD_SEPARATOR)
+        .next()
+        .unwrap_or_default();
+    Ok(sha.to_owned())
+}
+
+/// Get the commit and its ancestors, as far as the parents are known
+fn ancestors<'a>(sha: &str, parents: &HashMap<&'a str, &'a [String]>) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut pending = vec![sha];
+    while let Some(sha) = pending.pop() {
+        if let Some((&sha, shas)) = parents.get_key_value(sha) {
+            if seen.insert(sha) {
+                pending.extend(shas.iter().map(String::as_str));
+            }
+        }
+    }
+    seen
+}
+
+/// Get the packages of the Cargo workspace in the directory (none, if it has no workspace manifest)
+pub fn cargo_members(dir: &Path) -> Vec<Package> {
+    let manifest = match read_manifest(&dir.join("Cargo.toml")) {
+        Some(manifest) => manifest,
+        None => return Vec::new(),
+    };
+    let list = |key: &str| -> Vec<String> {
+        manifest
+            .get("workspace")
+            .and_then(|w| w.get(key))
+            .and_then(Value::as_array)
+            .map(|v| {
+                v.iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    // Globs leave out the `.` components, so the members are relative to the directory without them
+    let dir: PathBuf = dir
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let excluded = list("exclude");
+    let mut packages = Vec::new();
+    for member in list("members") {
+        let pattern = Path::new(&Pattern::escape(&dir.to_string_lossy())).join(&member);
+        let paths = match glob(&pattern.to_string_lossy()) {
+            Ok(paths) => paths,
+            Err(why) => {
+                warn!("Workspace member '{}' is skipped (Reason: {})", member, why);
+                continue;
+            }
+        };
+        for path in paths.filter_map(|p| p.ok()) {
+            let relative = match path.strip_prefix(&dir) {
+                Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+                Err(_) => continue,
+            };
+            if excluded.contains(&relative) {
+                continue;
+            }
+            let name = read_manifest(&path.join("Cargo.toml")).and_then(|m| {
+                m.get("package")
+                    .and_then(|p| p.get("name"))
+                    .and_then(Value::as_str)
+                    .map(String::from)
+            });
+            match name {
+                Some(name) => packages.push(Package {
+                    name,
+                    paths: vec![relative],
+                    ..Package::default()
+                }),
+                None => warn!(
+                    "Workspace member '{}' is skipped (Reason: no package name)",
+                    relative
+                ),
+            }
+        }
+    }
+    packages
+}
+
+/// Read the Cargo manifest (if there is a valid one)
+fn read_manifest(file: &Path) -> Option<Value> {
+    let text = fs::read_to_string(file).ok()?;
+    match toml::from_str(&text) {
+        Ok(manifest) => Some(manifest),
+        Err(why) => {
+            warn!("File '{}' is ignored (Reason: {})", file.display(), why);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cargo_members, Workspace};
+    use git::{format_record, ChangedFiles, GitBackend};
+    use input::Configuration;
+    use std::env::temp_dir;
+    use std::fs;
+    use std::path::Path;
+    use Result;
+
+    /// A backend with a fixed linear log, newest first, and a tag on its second commit
+    struct FixedLog(Vec<(&'static str, &'static str, &'static str)>);
+
+    impl GitBackend for FixedLog {
+        fn root(&self) -> &Path {
+            Path::new(".")
+        }
+        fn in_git_repository(&self) -> Result<bool> {
+            Ok(true)
+        }
+        fn tags(&self) -> Result<Vec<String>> {
+            Ok(vec![String::from("foo-v0.1.0")])
+        }
+        fn reachable_tags(&self) -> Result<Vec<String>> {
+            self.tags()
+        }
+        fn commits_in_log(&self, _: &[String]) -> Result<String> {
+            let time = "Sun, 22 Oct 2017 17:26:56 -0400";
+            Ok(self
+                .0
+                .iter()
+                .map(|&(sha, body, _)| {
+                    let message = format!("{}\n\n{}", sha, body);
+                    format_record(sha, "", "Jane", "j@x.org", time, &message)
+                })
+                .collect())
+        }
+        fn changed_files(&self, _: &[String]) -> Result<Vec<ChangedFiles>> {
+            let shas: Vec<&str> = self.0.iter().map(|c| c.0).collect();
+            Ok(self
+                .0
+                .iter()
+                .enumerate()
+                .map(|(i, &(sha, _, files))| ChangedFiles {
+                    sha: sha.to_owned(),
+                    parents: shas.get(i + 1).map(|p| p.to_string()).into_iter().collect(),
+                    files: files.split(' ').map(String::from).collect(),
+                })
+                .collect())
+        }
+        fn get_commit_message(&self, _: &str) -> Result<String> {
+            Ok(format_record("c2", "", "", "", "", ""))
+        }
+        fn get_remote_url(&self, _: &str) -> Result<Option<String>> {
+            Ok(None)
+        }
+        fn check_mailmap(&self, contacts: &[String]) -> Result<Vec<String>> {
+            Ok(contacts.to_vec())
+        }
+    }
+
+    #[test]
+    fn workspace() {
+        let yml =
+            "preset: default\npackages: [{name: foo, paths: [foo]}, {name: bar, paths: [bar]}]";
+        let config = Configuration::from_yaml(yml).unwrap();
+        let git = FixedLog(vec![
+            ("c4", "- fix: Both", "foo/a.rs bar/b.rs"),
+            ("c3", "- fix: Bar", "bar/b.rs"),
+            ("c2", "- fix: Old foo", "foo/a.rs"),
+            ("c1", "- fix: Old bar", "bar/b.rs"),
+        ]);
+        let workspace = Workspace::from_config_with(&config, &git).unwrap();
+        let logs: Vec<(&str, &str, Vec<&str>)> = workspace
+            .packages
+            .iter()
+            .map(|p| {
+                let shas = p.changelog.commits.iter().map(|c| c.sha.as_str()).collect();
+                (p.package.name.as_str(), p.changelog.range.as_str(), shas)
+            })
+            .collect();
+        assert_eq!(
+            logs,
+            vec![
+                ("foo", "foo-v0.1.0..HEAD", vec!["c4"]),
+                ("bar", "HEAD", vec!["c1", "c3", "c4"]),
+            ]
+        );
+
+        // Only the package without releases is new to Jane
+        let first_time: Vec<bool> = workspace
+            .packages
+            .iter()
+            .map(|p| p.changelog.contributors[0].first_time)
+            .collect();
+        assert_eq!(first_time, vec![false, true]);
+    }
+
+    #[test]
+    #[cfg(feature = "handlebars")]
+    fn update() {
+        let yml = "preset: default\npackages: [{name: foo, paths: [foo]}]";
+        let config = Configuration::from_yaml(yml).unwrap();
+        let git = FixedLog(vec![
+            ("c3", "- fix: New foo", "foo/a.rs"),
+            ("c2", "- fix: Old foo", "foo/a.rs"),
+            ("c1", "- fix: Older foo", "foo/a.rs"),
+        ]);
+
+        // The older sections of the existing change log stay as they are
+        let existing = "# foo-v0.1.0 (2017-10-22)\n\n- Edited foo\n";
+        let mut workspace = Workspace::from_config_with(&config, &git).unwrap();
+        let log = workspace.packages.remove(0);
+        let updated = log.update_with(existing, &config, &git).unwrap();
+        assert!(updated.starts_with("# Unreleased"));
+        assert!(updated.contains("New foo"));
+        assert!(updated.ends_with(existing));
+
+        // A new change log gets the missing release from the same log
+        let mut workspace = Workspace::from_config_with(&config, &git).unwrap();
+        let log = workspace.packages.remove(0);
+        let created = log.update_with("", &config, &git).unwrap();
+        let release = created.find("# foo-v0.1.0").unwrap();
+        assert!(created[..release].contains("New foo"));
+        assert!(created[release..].contains("Old foo"));
+        assert!(created[release..].contains("Older foo"));
+        assert!(!created[release..].contains("New foo"));
+    }
+
+    #[test]
+    fn no_packages() {
+        let config = Configuration::from_yaml("preset: default").unwrap();
+        let git = FixedLog(Vec::new());
+        assert!(Workspace::from_config_with(&config, &git).is_err());
+    }
+
+    #[test]
+    fn cargo_workspace() {
+        let dir = temp_dir().join(format!("changelog-workspace-{}", ::std::process::id()));
+        let write = |file: &str, text: &str| {
+            let file = dir.join(file);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, text).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\", \"tool\"]\nexclude = [\"crates/skip\"]\n",
+        );
+        write("crates/a/Cargo.toml", "[package]\nname = \"alpha\"\n");
+        write("crates/skip/Cargo.toml", "[package]\nname = \"skip\"\n");
+        write("tool/Cargo.toml", "[package]\nname = \"tool\"\n");
+
+        let members: Vec<(String, Vec<String>)> = cargo_members(&dir)
+            .into_iter()
+            .map(|p| (p.name, p.paths))
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            members,
+            vec![
+                (String::from("alpha"), vec![String::from("crates/a")]),
+                (String::from("tool"), vec![String::from("tool")]),
+            ]
+        );
+        assert!(cargo_members(Path::new("src")).is_empty());
+    }
+}
@@ -52,6 +52,167 @@ fn library_example() {
     println!("{:#?}", changelog);
 }
 
+#[test]
+fn conventional_commits() {
+    let mut config = builtin_config();
+    config.conventions.parsing = Parsing::Conventional;
+    config
+        .conventions
+        .categories
+        .push(Keyword::new("feat", "Features"));
+    config
+        .conventions
+        .categories
+        .push(Keyword::new("docs", "Docs"));
+
+    let commit = |subject: &str, body: &str| {
+        let mut lines = vec!["abc", "Jane", "Sun, 22 Oct 2017 17:26:56 -0400", subject];
+        lines.extend(body.lines());
+        Commit::from_lines(lines.into_iter().map(String::from).collect())
+    };
+    let commits = vec![
+        commit("feat(api): add filters", ""),
+        commit("fix!: drop the v1 endpoint", ""),
+        commit(
+            "docs: explain things",
+            "BREAKING CHANGE: v1 clients must\nupgrade",
+        ),
+        commit("chore: nothing to see", ""),
+        commit("Merge branch 'foo'", ""),
+    ];
+    let log = ChangeLog::from(commits.into_iter(), &config);
+    assert_eq!(log.commits.len(), 3);
+
+    let changes = |scope: &str, category: &str| -> Vec<String> {
+        log.scopes
+            .iter()
+            .filter(|s| s.title == scope)
+            .flat_map(|s| s.categories.iter())
+            .filter(|c| c.title == category)
+            .flat_map(|c| c.changes.iter().map(|c| c.text.clone()))
+            .collect()
+    };
+    assert_eq!(changes("API", "Features"), vec![" add filters"]);
+    assert_eq!(changes("", "Docs"), vec![" explain things"]);
+    assert_eq!(
+        changes("", "Breaking Changes"),
+        vec![" drop the v1 endpoint", " v1 clients must\nupgrade"]
+    );
+}
+
+#[test]
+fn change_attribution() {
+    let config = builtin_config();
+    let log = ChangeLog::from(vec![readme_commit()].into_iter(), &config);
+    let changes: Vec<&Change> = log
+        .scopes
+        .iter()
+        .flat_map(|s| s.categories.iter())
+        .flat_map(|c| c.changes.iter())
+        .collect();
+    assert_eq!(changes.len(), 4);
+    for change in &changes {
+        assert_eq!(change.sha, "1d82af9a1bd05c100b7b50bdcda3db39a5cddcdf");
+        assert_eq!(change.author, "aaaaaa a a'aaaaa");
+        assert_eq!(change.time, "Sun, 22 Oct 2017 17:26:56 -0400");
+        assert_eq!(change.number, None);
+    }
+    let tags: Vec<(&str, &str)> = changes
+        .iter()
+        .map(|c| (c.scope_tag.as_str(), c.category_tag.as_str()))
+        .collect();
+    assert_eq!(
+        tags,
+        vec![("", "feature"), ("", "feature"), ("", ""), ("api", "break")]
+    );
+}
+
+#[test]
+fn contributors() {
+    let config = builtin_config();
+    let commit = |author: &str, body: &str| {
+        let mut lines = vec!["abc", author, "Sun, 22 Oct 2017 17:26:56 -0400", "foo"];
+        lines.extend(body.lines());
+        let mut commit = Commit::from_lines(lines.into_iter().map(String::from).collect());
+        commit.email = format!("{}@example.com", author.to_lowercase());
+        commit
+    };
+    let commits = vec![
+        commit("Jane", "- fix: a"),
+        commit("Jane", "- fix: b\n\nCo-authored-by: Joe <JOE@example.com>"),
+        commit("Joe", "untagged"),
+        commit("Joe", "Co-authored-by: Joe <joe@example.com>"),
+    ];
+    let log = ChangeLog::from(commits.into_iter(), &config);
+    let counts: Vec<(&str, usize)> = log
+        .contributors
+        .iter()
+        .map(|c| (c.name.as_str(), c.commits))
+        .collect();
+    assert_eq!(counts, vec![("Joe", 3), ("Jane", 2)]);
+}
+
+#[test]
+#[cfg(feature = "handlebars")]
+fn subject_references() {
+    let mut config = builtin_config();
+    config.output.remote = Some(String::from("no-such-remote"));
+    let subject = "Pick fixes (!456) (#7)";
+    let lines = vec![
+        "abc",
+        "Jane",
+        "Sun, 22 Oct 2017 17:26:56 -0400",
+        subject,
+        "- fix: Foo",
+    ];
+    let commit = Commit::from_lines(lines.into_iter().map(String::from).collect());
+    assert_eq!(commit.summary, "Pick fixes");
+    assert_eq!(commit.number, Some(7));
+    assert_eq!(commit.references[0].kind, ReferenceKind::Mr);
+
+    let log = ChangeLog::from(vec![commit].into_iter(), &config);
+    let md = render(&log, &config.output).unwrap();
+    assert!(md.contains("- Pick fixes (!456) (#7)"), "{}", md);
+}
+
+#[test]
+#[cfg(feature = "handlebars")]
+fn keep_a_changelog() {
+    let config = Configuration::from_yaml("preset: keepachangelog").unwrap();
+    let release = |tag: Option<&str>, body: &str| {
+        let lines = vec![
+            "abc",
+            "Jane",
+            "Sun, 22 Oct 2017 17:26:56 -0400",
+            "Subject",
+            body,
+        ];
+        let commit = Commit::from_lines(lines.into_iter().map(String::from).collect());
+        let mut changelog = ChangeLog::from(vec![commit].into_iter(), &config);
+        let from = if tag.is_some() { "v0.1.0" } else { "v0.2.0" };
+        let to = tag.unwrap_or("HEAD");
+        changelog.compare_url = Some(format!("https://x/compare/{}...{}", from, to));
+        Release {
+            tag: tag.map(String::from),
+            title: String::from(tag.unwrap_or("Unreleased")),
+            changelog,
+        }
+    };
+    let history = History {
+        releases: vec![
+            release(None, "- fix: Foo"),
+            release(Some("v0.2.0"), "- feature: Bar\nbaz"),
+        ],
+    };
+    let md = render_history(&history, &config.output).unwrap();
+    let expected = "## [Unreleased]\n\n### Fixed\n\n- Foo\n\n\
+                    ## [v0.2.0] - 2017-10-22\n\n### Added\n\n- Bar\n  baz\n\n\
+                    [Unreleased]: https://x/compare/v0.2.0...HEAD\n\
+                    [v0.2.0]: https://x/compare/v0.1.0...v0.2.0";
+    assert!(md.starts_with("# Changelog\n"), "{}", md);
+    assert!(md.ends_with(expected), "{}", md);
+}
+
 fn builtin_config() -> Configuration {
     Configuration::from_yaml(include_str!("../src/assets/changelog.yml")).unwrap()
 }